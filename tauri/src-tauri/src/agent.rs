@@ -0,0 +1,352 @@
+use crate::app_state::*;
+use crate::cameras::*;
+use crate::supervisor::*;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::Emitter;
+
+/// Binarios y archivos con los que arranca el agente, ya resueltos
+#[derive(Clone)]
+pub struct AgentLaunch {
+    pub mediamtx_bin: PathBuf,
+    pub mediamtx_config: PathBuf,
+    pub ffmpeg_bin: PathBuf,
+    pub cameras_config: PathBuf,
+    /// `None` si cloudflared no está disponible
+    pub cloudflared: Option<CloudflaredLaunch>,
+    pub ffmpeg_capabilities: Option<FfmpegCapabilities>,
+    pub watchdog_interval: Duration,
+    /// Iniciar las cámaras habilitadas (`autoStartCameras`); si no, quedan
+    /// cargadas y detenidas
+    pub auto_start_cameras: bool,
+}
+
+/// Inicia MediaMTX, Cloudflared, las cámaras habilitadas y el watchdog.
+/// Si MediaMTX no arranca se detiene lo que ya se había lanzado y el agente
+/// vuelve a `Stopped`. Sin `app_handle` no se emiten eventos (tests).
+pub async fn start_agent(
+    state: &AppState,
+    launch: AgentLaunch,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<()> {
+    transition_agent(state, app_handle, AgentState::Starting, "Start requested").await?;
+
+    let problems = match start_components(state, &launch, app_handle).await {
+        Ok(problems) => problems,
+        Err(e) => {
+            log::error!("Agent failed to start, rolling back: {:#}", e);
+            stop_components(state, app_handle).await;
+            transition_agent(state, app_handle, AgentState::Stopped, format!("Startup failed: {:#}", e)).await?;
+            return Err(e);
+        }
+    };
+
+    // Lanzar watchdog que reinicia MediaMTX y cámaras caídas
+    {
+        let watchdog_config = WatchdogConfig {
+            interval: launch.watchdog_interval,
+            mediamtx_bin: launch.mediamtx_bin.clone(),
+            mediamtx_config: launch.mediamtx_config.clone(),
+            cloudflared: launch.cloudflared.clone(),
+        };
+        let handle = spawn_watchdog(state.clone(), app_handle.cloned(), watchdog_config);
+        let mut watchdog = state.watchdog.lock().await;
+        *watchdog = Some(handle);
+    }
+
+    if problems.is_empty() {
+        transition_agent(state, app_handle, AgentState::Running, "All components started").await?;
+    } else {
+        transition_agent(state, app_handle, AgentState::Degraded, problems.join("; ")).await?;
+    }
+
+    log::info!("=== Agent Started Successfully ===");
+    Ok(())
+}
+
+/// Lanza los procesos del agente. MediaMTX es imprescindible: si falla se
+/// devuelve el error. Los problemas de Cloudflared y las cámaras no frenan el
+/// inicio y se devuelven para informar el estado `Degraded`.
+async fn start_components(
+    state: &AppState,
+    launch: &AgentLaunch,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let sink = LogSink::new(state.logs.clone(), app_handle.cloned());
+
+    // Iniciar MediaMTX y esperar a que acepte conexiones antes de lanzar las cámaras
+    log::info!("Starting MediaMTX...");
+    let readiness = state.config.lock().await.readiness_policy.clone();
+    let mediamtx_handle = launch_mediamtx(
+        state.launcher.as_ref(),
+        &launch.mediamtx_bin,
+        &launch.mediamtx_config,
+        &sink,
+        &readiness,
+    ).await
+        .map_err(|e| e.context("Failed to start MediaMTX"))?;
+
+    let reconnect_policy = state.config.lock().await.reconnect_policy.clone();
+    {
+        let mut mediamtx = state.mediamtx.lock().await;
+        mediamtx.stats = ReconnectStats::default();
+        mediamtx.reconnect_policy = reconnect_policy.clone();
+        mediamtx.set_running(mediamtx_handle);
+    }
+    log::info!("MediaMTX started successfully");
+
+    // Intentar iniciar Cloudflared
+    match &launch.cloudflared {
+        Some(cloudflared_launch) => {
+            let tunnel_sink = tunnel_sink(&sink, state.tunnel.clone(), app_handle.cloned());
+            let mut cloudflared = state.cloudflared.lock().await;
+            cloudflared.stats = ReconnectStats::default();
+            cloudflared.reconnect_policy = reconnect_policy.clone();
+            match launch_cloudflared(state.launcher.as_ref(), cloudflared_launch, &tunnel_sink) {
+                Ok(handle) => {
+                    cloudflared.set_running(handle);
+                    log::info!("Cloudflared started successfully");
+                }
+                Err(e) => {
+                    // El watchdog lo reintentará según su política
+                    log::warn!("Failed to start Cloudflared (non-critical): {}", e);
+                    problems.push(format!("Cloudflared failed to start: {}", e));
+                    let policy = cloudflared.reconnect_policy.clone();
+                    cloudflared.status = cloudflared.stats.record_failure(&policy);
+                    let phase = failure_phase(&cloudflared.status);
+                    emit_retry_event(app_handle, "cloudflared", phase, &cloudflared.stats, &policy);
+                }
+            }
+        }
+        None => {
+            log::info!("Cloudflared not started (no configuration)");
+        }
+    }
+
+    // Cargar y auto-iniciar cámaras habilitadas (limpiar estado previo primero)
+    {
+        // Terminar las tareas de cámaras previas antes de cargar desde config
+        let shutdown = state.config.lock().await.shutdown_policy.clone();
+        remove_all_cameras(&state.cameras, &shutdown).await;
+
+        let camera_launch = LaunchContext {
+            launcher: state.launcher.clone(),
+            ffmpeg_path: launch.ffmpeg_bin.clone(),
+            sink: sink.clone(),
+            ffmpeg_capabilities: launch.ffmpeg_capabilities.clone(),
+        };
+
+        match init_cameras_from_config(
+            &state.cameras,
+            &launch.cameras_config,
+            &camera_launch,
+            &reconnect_policy,
+            app_handle.cloned(),
+            launch.auto_start_cameras,
+        ).await {
+            Ok(Some(warning)) => {
+                let mut warnings = state.config_warnings.lock().await;
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Failed to init cameras: {:#}", e);
+                problems.push(format!("Failed to load cameras: {}", e));
+            }
+        }
+
+        let cameras = state.cameras.lock().await;
+        if let Some(problem) = cameras_problem(&cameras) {
+            problems.push(problem);
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Detiene el watchdog y todos los procesos del agente.
+/// Lo usan tanto `stop_agent` como el cierre de la aplicación.
+pub async fn shutdown_agent(
+    state: &AppState,
+    app_handle: Option<&tauri::AppHandle>,
+    reason: &str,
+) -> Result<()> {
+    log::info!("Stopping agent...");
+    transition_agent(state, app_handle, AgentState::Stopping, reason).await?;
+
+    // Detener watchdog antes que las cámaras para que no las reinicie
+    {
+        let handle = state.watchdog.lock().await.take();
+        if let Some(handle) = handle {
+            handle.stop().await;
+        }
+    }
+
+    stop_components(state, app_handle).await;
+
+    log::info!("Agent stopped successfully");
+    transition_agent(state, app_handle, AgentState::Stopped, "Agent stopped").await
+}
+
+/// Detiene el agente desde cualquier estado salvo `Stopped`. Si está
+/// arrancando o deteniéndose espera a que termine (o haga rollback) antes de
+/// decidir; el llamador pone el límite de tiempo.
+pub async fn shutdown_when_settled(
+    state: &AppState,
+    app_handle: Option<&tauri::AppHandle>,
+    reason: &str,
+) -> Result<()> {
+    loop {
+        let current = state.lifecycle.lock().await.state;
+        match current {
+            AgentState::Stopped => return Ok(()),
+            AgentState::Starting | AgentState::Stopping => {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            AgentState::Running | AgentState::Degraded => {
+                return shutdown_agent(state, app_handle, reason).await;
+            }
+        }
+    }
+}
+
+/// Detiene cámaras, Cloudflared y MediaMTX, en ese orden. Los errores se
+/// registran y no cortan la secuencia.
+async fn stop_components(state: &AppState, app_handle: Option<&tauri::AppHandle>) {
+    let shutdown = state.config.lock().await.shutdown_policy.clone();
+
+    // Detener todas las cámaras
+    if let Err(e) = stop_all_cameras(&state.cameras, &shutdown).await {
+        log::error!("Error stopping cameras: {}", e);
+    }
+
+    // Detener Cloudflared
+    {
+        let mut cloudflared = state.cloudflared.lock().await;
+        if let Some(handle) = cloudflared.process.as_mut() {
+            if let Err(e) = stop_process(handle, &shutdown).await {
+                log::error!("Error stopping Cloudflared: {}", e);
+            }
+        }
+        cloudflared.process = None;
+        cloudflared.resources.clear();
+        cloudflared.status = ProcessStatus::Stopped;
+        cloudflared.stats.cancel_retry();
+        cloudflared.stats.reset_quarantine();
+        mark_tunnel_disconnected(&state.tunnel, app_handle);
+    }
+
+    // Detener MediaMTX
+    {
+        let mut mediamtx = state.mediamtx.lock().await;
+        if let Some(handle) = mediamtx.process.as_mut() {
+            if let Err(e) = stop_process(handle, &shutdown).await {
+                log::error!("Error stopping MediaMTX: {}", e);
+            }
+        }
+        mediamtx.process = None;
+        mediamtx.resources.clear();
+        mediamtx.status = ProcessStatus::Stopped;
+        mediamtx.stats.cancel_retry();
+        mediamtx.stats.reset_quarantine();
+    }
+}
+
+/// Cambia el estado del agente (si la transición es válida) y emite
+/// `agent-state-changed`
+pub async fn transition_agent(
+    state: &AppState,
+    app_handle: Option<&tauri::AppHandle>,
+    next: AgentState,
+    reason: impl Into<String>,
+) -> Result<()> {
+    let change = state.lifecycle.lock().await.transition(next, reason)?;
+    log::info!("Agent state: {} -> {} ({})", change.previous.as_str(), change.state.as_str(), change.reason);
+
+    if let Some(app_handle) = app_handle {
+        crate::update_tray_tooltip(app_handle, &agent_tooltip(&change));
+        if let Err(e) = app_handle.emit("agent-state-changed", change) {
+            log::warn!("Failed to emit agent-state-changed: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Texto del tooltip del tray para un estado del agente. Los estados con
+/// problemas incluyen el motivo para verlo sin abrir la ventana.
+pub fn agent_tooltip(change: &AgentStateChange) -> String {
+    let failed_start = change.state == AgentState::Stopped && change.previous == AgentState::Starting;
+    if failed_start || change.state == AgentState::Degraded {
+        format!("Stream Agent: {} - {}", change.state.as_str(), change.reason)
+    } else {
+        format!("Stream Agent: {}", change.state.as_str())
+    }
+}
+
+/// Describe las cámaras habilitadas que deberían transmitir y no lo hacen, o
+/// `None` si no hay ninguna. Las detenidas a propósito (o no iniciadas por
+/// `autoStartCameras`) no cuentan.
+pub fn cameras_problem(cameras: &HashMap<String, CameraHandle>) -> Option<String> {
+    let mut down: Vec<String> = cameras.values()
+        .map(|camera| camera.snapshot())
+        .filter(|snapshot| {
+            snapshot.config.enabled
+                && !matches!(snapshot.status, ProcessStatus::Running | ProcessStatus::Stopped)
+        })
+        .map(|snapshot| format!("{} ({})", snapshot.config.id, snapshot.status.as_str()))
+        .collect();
+    if down.is_empty() {
+        return None;
+    }
+    down.sort();
+    Some(format!("Cameras not streaming: {}", down.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_tooltip() {
+        let change = |previous, state, reason: &str| AgentStateChange {
+            previous,
+            state,
+            reason: reason.to_string(),
+            at: chrono::Utc::now(),
+        };
+        assert_eq!(
+            agent_tooltip(&change(AgentState::Starting, AgentState::Running, "All components started")),
+            "Stream Agent: running"
+        );
+        assert_eq!(
+            agent_tooltip(&change(AgentState::Starting, AgentState::Stopped, "Startup failed: port in use")),
+            "Stream Agent: stopped - Startup failed: port in use"
+        );
+        assert_eq!(
+            agent_tooltip(&change(AgentState::Stopping, AgentState::Stopped, "Agent stopped")),
+            "Stream Agent: stopped"
+        );
+    }
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let mut lifecycle = AgentLifecycle::new();
+        assert!(lifecycle.transition(AgentState::Running, "skip starting").is_err());
+        assert!(lifecycle.transition(AgentState::Stopping, "not running").is_err());
+
+        let change = lifecycle.transition(AgentState::Starting, "Start requested").unwrap();
+        assert_eq!(change.previous, AgentState::Stopped);
+        assert!(lifecycle.transition(AgentState::Starting, "twice").is_err());
+        lifecycle.transition(AgentState::Degraded, "Cloudflared is down").unwrap();
+        lifecycle.transition(AgentState::Running, "All components healthy").unwrap();
+        lifecycle.transition(AgentState::Stopping, "Stop requested").unwrap();
+        assert!(lifecycle.transition(AgentState::Running, "too late").is_err());
+        lifecycle.transition(AgentState::Stopped, "Agent stopped").unwrap();
+        assert_eq!(lifecycle.reason, "Agent stopped");
+    }
+}
@@ -1,31 +1,66 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::process::Child;
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::{Duration, SystemTime};
 use tokio::sync::Mutex;
 use chrono::{DateTime, Utc};
+use crate::cameras::CameraHandle;
+use crate::supervisor::{
+    jittered_delay, random_unit, FailureDetector, FailureInfo, FfmpegProgress, JitterMode, ManagedChild,
+    ProcessLauncher, ResolvedBinary, ResourceMonitor, ResourceUsage, SystemLauncher, ToolCapabilities,
+    WatchdogHandle,
+};
 
 /// Estado global de la aplicación
 #[derive(Clone)]
 pub struct AppState {
-    pub cameras: Arc<Mutex<HashMap<String, CameraRuntime>>>,
-    pub mediamtx_process: Arc<Mutex<Option<ProcessHandle>>>,
-    pub cloudflared_process: Arc<Mutex<Option<ProcessHandle>>>,
+    /// Solo handles: cada cámara corre en su propia tarea (ver `CameraHandle`)
+    pub cameras: Arc<Mutex<HashMap<String, CameraHandle>>>,
+    pub mediamtx: Arc<Mutex<ServiceRuntime>>,
+    pub cloudflared: Arc<Mutex<ServiceRuntime>>,
+    /// Compartido con el hilo que lee la salida de cloudflared
+    pub tunnel: Arc<StdMutex<TunnelStatus>>,
     pub config: Arc<Mutex<AgentConfig>>,
-    pub is_running: Arc<Mutex<bool>>,
+    pub lifecycle: Arc<Mutex<AgentLifecycle>>,
     pub logs: Arc<Mutex<LogBuffer>>,
+    pub watchdog: Arc<Mutex<Option<WatchdogHandle>>>,
+    /// Resultado de la última consulta a los binarios
+    pub capabilities: Arc<Mutex<Option<ToolCapabilities>>>,
+    /// Lanza MediaMTX, FFmpeg y cloudflared (simulado en los tests)
+    pub launcher: Arc<dyn ProcessLauncher>,
+    /// Avisos sobre los archivos de configuración (ej. `cameras.json`
+    /// restaurado desde un backup); duran hasta cerrar la app
+    pub config_warnings: Arc<Mutex<Vec<String>>>,
+    /// Binarios con los que se lanzó el agente; mientras no esté detenido el
+    /// estado informa estos en lugar de volver a buscarlos
+    pub binaries: Arc<Mutex<Vec<ResolvedBinary>>>,
+    /// Limpieza de procesos huérfanos de la ejecución anterior; el agente no
+    /// se inicia hasta que termina (ver `launch_agent`)
+    pub orphan_cleanup: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<usize>>>>,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(config: AgentConfig) -> Self {
+        Self::with_launcher(config, Arc::new(SystemLauncher))
+    }
+
+    pub fn with_launcher(config: AgentConfig, launcher: Arc<dyn ProcessLauncher>) -> Self {
         Self {
             cameras: Arc::new(Mutex::new(HashMap::new())),
-            mediamtx_process: Arc::new(Mutex::new(None)),
-            cloudflared_process: Arc::new(Mutex::new(None)),
-            config: Arc::new(Mutex::new(AgentConfig::default())),
-            is_running: Arc::new(Mutex::new(false)),
+            mediamtx: Arc::new(Mutex::new(ServiceRuntime::new())),
+            cloudflared: Arc::new(Mutex::new(ServiceRuntime::new())),
+            tunnel: Arc::new(StdMutex::new(TunnelStatus::default())),
+            config: Arc::new(Mutex::new(config)),
+            lifecycle: Arc::new(Mutex::new(AgentLifecycle::new())),
             logs: Arc::new(Mutex::new(LogBuffer::new())),
+            watchdog: Arc::new(Mutex::new(None)),
+            capabilities: Arc::new(Mutex::new(None)),
+            launcher,
+            config_warnings: Arc::new(Mutex::new(Vec::new())),
+            binaries: Arc::new(Mutex::new(Vec::new())),
+            orphan_cleanup: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -44,6 +79,16 @@ pub struct CameraConfig {
     pub quality: QualityPreset,
     #[serde(default = "default_audio")]
     pub audio_mode: AudioMode,
+    /// Política propia de la cámara; `None` usa la global de `config.json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect: Option<ReconnectPolicy>,
+}
+
+impl CameraConfig {
+    /// Política que aplica a la cámara: la propia o, si no tiene, la global
+    pub fn effective_reconnect_policy(&self, default: &ReconnectPolicy) -> ReconnectPolicy {
+        self.reconnect.clone().unwrap_or_else(|| default.clone())
+    }
 }
 
 fn default_encoding() -> EncodingMode {
@@ -81,47 +126,241 @@ pub enum AudioMode {
     Transcode,
 }
 
-/// Estado runtime de una cámara
+/// Estado runtime de una cámara, propiedad de su tarea
 pub struct CameraRuntime {
     pub config: CameraConfig,
     pub process: Option<ProcessHandle>,
     pub status: ProcessStatus,
     pub stats: ReconnectStats,
     pub reconnect_policy: ReconnectPolicy,
+    /// Compartido con el hilo que lee el stderr de FFmpeg
+    pub metrics: Arc<StdMutex<MetricsRecorder>>,
+    /// Errores reconocidos en el stderr del proceso actual
+    pub failure_detector: Arc<StdMutex<FailureDetector>>,
+    /// Motivo de la última caída de FFmpeg
+    pub last_failure: Option<FailureInfo>,
+    /// CPU y memoria de FFmpeg, muestreados por el watchdog
+    pub resources: Arc<StdMutex<ResourceMonitor>>,
 }
 
 impl CameraRuntime {
-    pub fn new(config: CameraConfig) -> Self {
+    pub fn new(config: CameraConfig, default_policy: &ReconnectPolicy) -> Self {
         Self {
+            reconnect_policy: config.effective_reconnect_policy(default_policy),
             config,
+            process: None,
+            status: ProcessStatus::Stopped,
+            stats: ReconnectStats::default(),
+            metrics: Arc::new(StdMutex::new(MetricsRecorder::new())),
+            failure_detector: Arc::new(StdMutex::new(FailureDetector::default())),
+            last_failure: None,
+            resources: Arc::new(StdMutex::new(ResourceMonitor::new())),
+        }
+    }
+
+    pub fn current_metrics(&self) -> StreamMetrics {
+        self.metrics.lock()
+            .map(|m| m.current().clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Copia del estado de una cámara que publica su tarea para consultas
+#[derive(Debug, Clone)]
+pub struct CameraSnapshot {
+    pub config: CameraConfig,
+    pub status: ProcessStatus,
+    pub stats: ReconnectStats,
+    pub last_failure: Option<FailureInfo>,
+}
+
+impl CameraSnapshot {
+    pub fn from_runtime(runtime: &CameraRuntime) -> Self {
+        Self {
+            config: runtime.config.clone(),
+            status: runtime.status.clone(),
+            stats: runtime.stats.clone(),
+            last_failure: runtime.last_failure.clone(),
+        }
+    }
+}
+
+/// Métricas en vivo del stream de una cámara (desde el progreso de FFmpeg)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamMetrics {
+    pub frame: u64,
+    pub fps: f32,
+    pub bitrate_kbps: Option<f32>,
+    pub speed: Option<f32>,
+    pub drop_frames: u64,
+    pub dup_frames: u64,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Última vez que el contador de frames avanzó
+    pub last_frame_at: Option<DateTime<Utc>>,
+}
+
+/// Muestra del historial de métricas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSample {
+    pub timestamp: DateTime<Utc>,
+    pub fps: f32,
+    pub bitrate_kbps: Option<f32>,
+    pub speed: Option<f32>,
+}
+
+/// Acumula las métricas actuales y un historial corto (una muestra por segundo)
+#[derive(Debug)]
+pub struct MetricsRecorder {
+    current: StreamMetrics,
+    history: VecDeque<MetricsSample>,
+    max_samples: usize,
+}
+
+impl MetricsRecorder {
+    pub fn new() -> Self {
+        Self {
+            current: StreamMetrics::default(),
+            history: VecDeque::new(),
+            max_samples: 300, // 5 minutos
+        }
+    }
+
+    pub fn record(&mut self, progress: &FfmpegProgress) {
+        let now = Utc::now();
+        let last_frame_at = if progress.frame > self.current.frame {
+            Some(now)
+        } else {
+            self.current.last_frame_at
+        };
+
+        self.current = StreamMetrics {
+            frame: progress.frame,
+            fps: progress.fps,
+            bitrate_kbps: progress.bitrate_kbps,
+            speed: progress.speed,
+            drop_frames: progress.drop,
+            dup_frames: progress.dup,
+            updated_at: Some(now),
+            last_frame_at,
+        };
+
+        let due = self.history.back()
+            .map_or(true, |last| now.signed_duration_since(last.timestamp).num_milliseconds() >= 1000);
+        if due {
+            self.history.push_back(MetricsSample {
+                timestamp: now,
+                fps: progress.fps,
+                bitrate_kbps: progress.bitrate_kbps,
+                speed: progress.speed,
+            });
+            if self.history.len() > self.max_samples {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    /// Limpia los valores actuales al relanzar FFmpeg (el contador de frames reinicia)
+    pub fn reset(&mut self) {
+        self.current = StreamMetrics::default();
+    }
+
+    pub fn current(&self) -> &StreamMetrics {
+        &self.current
+    }
+
+    /// Historial de los últimos `window_secs` segundos (o completo si es `None`)
+    pub fn history(&self, window_secs: Option<u64>) -> Vec<MetricsSample> {
+        match window_secs {
+            Some(secs) => {
+                let since = Utc::now() - chrono::Duration::seconds(secs as i64);
+                self.history.iter()
+                    .filter(|s| s.timestamp >= since)
+                    .cloned()
+                    .collect()
+            }
+            None => self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Estado runtime de un servicio de soporte (MediaMTX, Cloudflared)
+pub struct ServiceRuntime {
+    pub process: Option<ProcessHandle>,
+    pub status: ProcessStatus,
+    pub stats: ReconnectStats,
+    pub reconnect_policy: ReconnectPolicy,
+    pub resources: ResourceMonitor,
+}
+
+impl ServiceRuntime {
+    pub fn new() -> Self {
+        Self {
             process: None,
             status: ProcessStatus::Stopped,
             stats: ReconnectStats::default(),
             reconnect_policy: ReconnectPolicy::default(),
+            resources: ResourceMonitor::new(),
         }
     }
+
+    /// Registra un proceso recién lanzado y listo
+    pub fn set_running(&mut self, handle: ProcessHandle) {
+        self.resources.clear();
+        self.process = Some(handle);
+        self.status = ProcessStatus::Running;
+        self.stats.cancel_retry();
+    }
+
+    pub fn uptime_secs(&self) -> u64 {
+        self.process.as_ref().map_or(0, |p| p.uptime().as_secs())
+    }
+}
+
+/// Estado de conexión del túnel de Cloudflare, según los logs de cloudflared
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelStatus {
+    pub connected: bool,
+    /// Conexiones registradas con el edge de Cloudflare
+    pub connections: u32,
+    /// URL pública (solo en modo Quick Tunnel)
+    pub url: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// Handle a un proceso externo
 #[derive(Debug)]
 pub struct ProcessHandle {
-    pub child: Option<Child>,
+    pub child: Option<Box<dyn ManagedChild>>,
     pub pid: Option<u32>,
     pub started_at: SystemTime,
     pub name: String,
+    /// Entrada a escribir por stdin para pedir un cierre ordenado (ej. `q` en FFmpeg)
+    pub quit_input: Option<&'static str>,
+    /// Código de salida, una vez que el proceso terminó
+    pub exit_status: Option<ExitStatus>,
 }
 
 impl ProcessHandle {
-    pub fn new(name: String, child: Child) -> Self {
+    pub fn new(name: String, child: Box<dyn ManagedChild>) -> Self {
         let pid = child.id();
         Self {
             child: Some(child),
             pid: Some(pid),
             started_at: SystemTime::now(),
             name,
+            quit_input: None,
+            exit_status: None,
         }
     }
 
+    pub fn with_quit_input(mut self, input: &'static str) -> Self {
+        self.quit_input = Some(input);
+        self
+    }
+
     pub fn uptime(&self) -> Duration {
         SystemTime::now()
             .duration_since(self.started_at)
@@ -136,9 +375,23 @@ pub enum ProcessStatus {
     Stopped,
     Running,
     Reconnecting,
+    /// Demasiados reinicios seguidos: se deja en paz hasta que pase el cooldown
+    Quarantined,
     Failed,
 }
 
+impl ProcessStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessStatus::Stopped => "stopped",
+            ProcessStatus::Running => "running",
+            ProcessStatus::Reconnecting => "reconnecting",
+            ProcessStatus::Quarantined => "quarantined",
+            ProcessStatus::Failed => "failed",
+        }
+    }
+}
+
 /// Política de reconexión
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReconnectPolicy {
@@ -148,6 +401,36 @@ pub struct ReconnectPolicy {
     pub backoff_multiplier: f32,
     pub max_delay_ms: u64,
     pub reset_counter_after_ms: u64,
+    /// Tiempo sin avanzar frames tras el cual se reinicia el proceso (0 = deshabilitado)
+    #[serde(default = "default_stall_timeout")]
+    pub stall_timeout_ms: u64,
+    #[serde(default)]
+    pub jitter: JitterMode,
+    /// Reinicios dentro de `quarantine_window_ms` a partir de los cuales el
+    /// proceso entra en cuarentena (0 = deshabilitado)
+    #[serde(default = "default_quarantine_after_restarts")]
+    pub quarantine_after_restarts: u32,
+    #[serde(default = "default_quarantine_window")]
+    pub quarantine_window_ms: u64,
+    /// Tiempo en cuarentena antes de intentar una vez más
+    #[serde(default = "default_quarantine_cooldown")]
+    pub quarantine_cooldown_ms: u64,
+}
+
+fn default_stall_timeout() -> u64 {
+    20000
+}
+
+fn default_quarantine_after_restarts() -> u32 {
+    5
+}
+
+fn default_quarantine_window() -> u64 {
+    600000 // 10 minutos
+}
+
+fn default_quarantine_cooldown() -> u64 {
+    1800000 // 30 minutos
 }
 
 impl Default for ReconnectPolicy {
@@ -159,6 +442,82 @@ impl Default for ReconnectPolicy {
             backoff_multiplier: 2.0,
             max_delay_ms: 60000,
             reset_counter_after_ms: 300000, // 5 minutos
+            stall_timeout_ms: default_stall_timeout(),
+            jitter: JitterMode::Full,
+            quarantine_after_restarts: default_quarantine_after_restarts(),
+            quarantine_window_ms: default_quarantine_window(),
+            quarantine_cooldown_ms: default_quarantine_cooldown(),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Rechaza valores que harían reintentar en bucle o nunca
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.retry_delay_ms == 0 {
+            anyhow::bail!("retry_delay_ms must be greater than 0");
+        }
+        if !self.backoff_multiplier.is_finite() || self.backoff_multiplier < 1.0 {
+            anyhow::bail!("backoff_multiplier must be at least 1.0");
+        }
+        if self.max_delay_ms < self.retry_delay_ms {
+            anyhow::bail!("max_delay_ms must not be lower than retry_delay_ms");
+        }
+        if self.stall_timeout_ms > 0 && self.stall_timeout_ms < 5000 {
+            anyhow::bail!("stall_timeout_ms must be 0 (disabled) or at least 5000");
+        }
+        if self.quarantine_after_restarts > 0 {
+            if self.quarantine_window_ms == 0 {
+                anyhow::bail!("quarantine_window_ms must be greater than 0 when quarantine is enabled");
+            }
+            if self.quarantine_cooldown_ms == 0 {
+                anyhow::bail!("quarantine_cooldown_ms must be greater than 0 when quarantine is enabled");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tiempos de espera para detener procesos de forma ordenada
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownPolicy {
+    /// Espera tras pedir el cierre por stdin (`q` en FFmpeg)
+    pub quit_timeout_ms: u64,
+    /// Espera tras SIGTERM antes de forzar con SIGKILL. En Windows no hay
+    /// SIGTERM y se pasa directo al kill
+    pub terminate_grace_ms: u64,
+}
+
+impl Default for ShutdownPolicy {
+    fn default() -> Self {
+        Self {
+            quit_timeout_ms: 3000,
+            terminate_grace_ms: 5000,
+        }
+    }
+}
+
+impl ShutdownPolicy {
+    /// Lo más que espera la política antes de forzar un proceso con SIGKILL
+    pub fn max_stop_time(&self) -> Duration {
+        Duration::from_millis(self.quit_timeout_ms.saturating_add(self.terminate_grace_ms))
+    }
+}
+
+/// Espera a que un servicio dependiente (ej. MediaMTX) acepte conexiones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessPolicy {
+    pub timeout_ms: u64,
+    pub interval_ms: u64,
+}
+
+impl Default for ReadinessPolicy {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 15000,
+            interval_ms: 250,
         }
     }
 }
@@ -171,6 +530,27 @@ pub struct ReconnectStats {
     pub last_stable_time: Option<DateTime<Utc>>,
     pub consecutive_failures: u32,
     pub total_uptime_secs: u64,
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// Delay del último reintento agendado (base del jitter decorrelacionado)
+    #[serde(default)]
+    pub last_delay_ms: Option<u64>,
+    /// Fallos dentro de la ventana de cuarentena
+    #[serde(skip)]
+    pub recent_failures: VecDeque<DateTime<Utc>>,
+    #[serde(default)]
+    pub quarantine: Option<QuarantineInfo>,
+    /// Recién salido de cuarentena: un fallo más antes de estabilizarse la reactiva
+    #[serde(default)]
+    pub on_probation: bool,
+}
+
+/// Por qué y hasta cuándo un proceso está en cuarentena
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineInfo {
+    pub reason: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
 }
 
 impl ReconnectStats {
@@ -182,55 +562,413 @@ impl ReconnectStats {
 
     pub fn record_success(&mut self) {
         self.consecutive_failures = 0;
+        self.last_delay_ms = None;
         self.last_stable_time = Some(Utc::now());
     }
 
-    pub fn should_reset_counter(&self, policy: &ReconnectPolicy) -> bool {
-        if let Some(last_stable) = self.last_stable_time {
-            let elapsed = Utc::now().signed_duration_since(last_stable);
-            elapsed.num_milliseconds() as u64 >= policy.reset_counter_after_ms
+    pub fn schedule_retry(&mut self, delay_ms: u64) {
+        self.last_delay_ms = Some(delay_ms);
+        self.next_retry_at = Some(Utc::now() + chrono::Duration::milliseconds(delay_ms as i64));
+    }
+
+    /// Registra un fallo y agenda el próximo intento según la política, con
+    /// el jitter que indique. Devuelve el nuevo estado: `Reconnecting`, o
+    /// `Failed` si no quedan reintentos.
+    pub fn record_failure(&mut self, policy: &ReconnectPolicy) -> ProcessStatus {
+        self.record_restart();
+
+        let now = Utc::now();
+        let window_start = now - chrono::Duration::milliseconds(policy.quarantine_window_ms as i64);
+        self.recent_failures.push_back(now);
+        while self.recent_failures.front().map_or(false, |at| *at < window_start) {
+            self.recent_failures.pop_front();
+        }
+
+        if policy.enabled && policy.quarantine_after_restarts > 0 {
+            if self.on_probation {
+                return self.enter_quarantine("failed again after quarantine cooldown".to_string(), policy);
+            }
+            let recent = self.recent_failures.len() as u32;
+            if recent > policy.quarantine_after_restarts {
+                let reason = format!(
+                    "{} restarts in the last {}s",
+                    recent, policy.quarantine_window_ms / 1000
+                );
+                return self.enter_quarantine(reason, policy);
+            }
+        }
+
+        if !policy.enabled || self.consecutive_failures > policy.max_retries {
+            self.next_retry_at = None;
+            ProcessStatus::Failed
         } else {
-            false
+            let delay_ms = jittered_delay(self.consecutive_failures, self.last_delay_ms, policy, random_unit());
+            self.schedule_retry(delay_ms);
+            ProcessStatus::Reconnecting
         }
     }
+
+    /// Registra un fallo que no se arregla reintentando (credenciales, códec):
+    /// el proceso pasa directo a `Failed` sin agendar intentos
+    pub fn record_fatal_failure(&mut self) -> ProcessStatus {
+        self.record_restart();
+        self.next_retry_at = None;
+        ProcessStatus::Failed
+    }
+
+    /// Deja el proceso quieto durante `quarantine_cooldown_ms`
+    fn enter_quarantine(&mut self, reason: String, policy: &ReconnectPolicy) -> ProcessStatus {
+        let since = Utc::now();
+        let until = since + chrono::Duration::milliseconds(policy.quarantine_cooldown_ms as i64);
+
+        self.quarantine = Some(QuarantineInfo { reason, since, until });
+        self.on_probation = false;
+        self.recent_failures.clear();
+        self.last_delay_ms = Some(policy.quarantine_cooldown_ms);
+        self.next_retry_at = Some(until);
+        ProcessStatus::Quarantined
+    }
+
+    /// Sale de cuarentena para el intento posterior al cooldown. Si ese
+    /// intento falla antes de estabilizarse, vuelve a cuarentena.
+    pub fn begin_probation(&mut self) {
+        self.quarantine = None;
+        self.on_probation = true;
+    }
+
+    /// Olvida la cuarentena y los fallos recientes (acción manual del operador)
+    pub fn reset_quarantine(&mut self) {
+        self.quarantine = None;
+        self.on_probation = false;
+        self.recent_failures.clear();
+    }
+
+    /// `true` si hay un reintento agendado y ya venció
+    pub fn is_retry_due(&self) -> bool {
+        match self.next_retry_at {
+            Some(at) => Utc::now() >= at,
+            None => false,
+        }
+    }
+
+    /// Descarta el reintento agendado (ej. el usuario detuvo el proceso)
+    pub fn cancel_retry(&mut self) {
+        self.next_retry_at = None;
+    }
+
+    /// Resetea el contador de fallos si el proceso lleva `reset_counter_after_ms`
+    /// corriendo. Devuelve `true` si lo reseteó.
+    pub fn reset_if_stable(&mut self, uptime_ms: u64, policy: &ReconnectPolicy) -> bool {
+        if (self.consecutive_failures == 0 && !self.on_probation) || uptime_ms < policy.reset_counter_after_ms {
+            return false;
+        }
+        self.record_success();
+        self.reset_quarantine();
+        true
+    }
 }
 
-/// Configuración general del agente
+/// Configuración general del agente (`config.json`). Las claves que falten
+/// toman el valor por defecto.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct AgentConfig {
+    /// Los archivos viejos se migran al cargarlos (ver `config_manager`)
+    pub schema_version: u32,
     pub server_url: String,
     pub location_id: String,
     pub location_name: String,
+    /// Iniciar el agente al abrir la aplicación
+    pub auto_start_agent: bool,
+    /// Iniciar las cámaras habilitadas al iniciar el agente
+    pub auto_start_cameras: bool,
+    pub tunnel_enabled: bool,
+    pub tunnel_mode: TunnelMode,
     pub tunnel_name: String,
+    /// Túnel con nombre a usar; `None` usa el primero que encuentre
     pub tunnel_id: Option<String>,
     pub tunnel_hostname: Option<String>,
+    pub shutdown_policy: ShutdownPolicy,
+    pub readiness_policy: ReadinessPolicy,
+    /// Política de reconexión por defecto de servicios y cámaras
+    pub reconnect_policy: ReconnectPolicy,
+    /// Rutas explícitas de binarios por nombre lógico (`"ffmpeg"`)
+    pub binary_paths: HashMap<String, PathBuf>,
 }
 
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
+            schema_version: CONFIG_SCHEMA_VERSION,
             server_url: "https://padel.noaservice.org".to_string(),
             location_id: "1".to_string(),
             location_name: "Ubicación Principal".to_string(),
+            auto_start_agent: false,
+            auto_start_cameras: true,
+            tunnel_enabled: true,
+            tunnel_mode: TunnelMode::Auto,
             tunnel_name: "stream-agent".to_string(),
             tunnel_id: None,
             tunnel_hostname: None,
+            shutdown_policy: ShutdownPolicy::default(),
+            readiness_policy: ReadinessPolicy::default(),
+            reconnect_policy: ReconnectPolicy::default(),
+            binary_paths: HashMap::new(),
         }
     }
 }
 
+impl AgentConfig {
+    /// Valida cada campo y devuelve todos los errores encontrados
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let mut check = |field: &str, ok: bool, message: &str| {
+            if !ok {
+                errors.push(FieldError { field: field.to_string(), message: message.to_string() });
+            }
+        };
+
+        let server_url = self.server_url.trim();
+        check(
+            "serverUrl",
+            (server_url.starts_with("http://") || server_url.starts_with("https://"))
+                && !server_url.contains(char::is_whitespace),
+            "must be an http:// or https:// URL",
+        );
+        check("locationId", !self.location_id.trim().is_empty(), "must not be empty");
+        check("locationName", !self.location_name.trim().is_empty(), "must not be empty");
+        check("locationName", self.location_name.chars().count() <= 100, "must be at most 100 characters");
+        check(
+            "tunnelName",
+            !self.tunnel_name.is_empty()
+                && self.tunnel_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "must contain only letters, digits, '-' or '_'",
+        );
+        if let Some(hostname) = &self.tunnel_hostname {
+            check(
+                "tunnelHostname",
+                hostname.contains('.')
+                    && hostname.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'),
+                "must be a hostname like cams.example.com, without scheme or path",
+            );
+        }
+        check(
+            "readinessPolicy",
+            self.readiness_policy.interval_ms > 0 && self.readiness_policy.timeout_ms >= self.readiness_policy.interval_ms,
+            "intervalMs must be greater than 0 and timeoutMs at least intervalMs",
+        );
+        check(
+            "shutdownPolicy",
+            self.shutdown_policy.quit_timeout_ms <= 60000 && self.shutdown_policy.terminate_grace_ms <= 60000,
+            "timeouts must be at most 60000ms",
+        );
+        if let Err(e) = self.reconnect_policy.validate() {
+            check("reconnectPolicy", false, &e.to_string());
+        }
+        for (name, path) in &self.binary_paths {
+            check(
+                "binaryPaths",
+                ["mediamtx", "ffmpeg", "cloudflared"].contains(&name.as_str()),
+                &format!("unknown binary '{}'", name),
+            );
+            check("binaryPaths", path.is_absolute(), &format!("path for '{}' must be absolute", name));
+        }
+
+        errors
+    }
+
+    /// Vuelve un campo a su valor por defecto (por nombre en `config.json`)
+    pub fn reset_field(&mut self, field: &str) {
+        let default = AgentConfig::default();
+        match field {
+            "serverUrl" => self.server_url = default.server_url,
+            "locationId" => self.location_id = default.location_id,
+            "locationName" => self.location_name = default.location_name,
+            "tunnelName" => self.tunnel_name = default.tunnel_name,
+            "tunnelHostname" => self.tunnel_hostname = default.tunnel_hostname,
+            "readinessPolicy" => self.readiness_policy = default.readiness_policy,
+            "shutdownPolicy" => self.shutdown_policy = default.shutdown_policy,
+            "reconnectPolicy" => self.reconnect_policy = default.reconnect_policy,
+            "binaryPaths" => self.binary_paths = default.binary_paths,
+            _ => log::warn!("Cannot reset unknown config field: {}", field),
+        }
+    }
+}
+
+/// Cómo se expone MediaMTX con cloudflared
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelMode {
+    /// Túnel con nombre si hay uno configurado en el sistema, si no Quick Tunnel
+    #[default]
+    Auto,
+    /// Quick Tunnel (`*.trycloudflare.com`), sin cuenta
+    Quick,
+    /// Túnel con nombre existente; falla si no hay credenciales
+    Named,
+}
+
+/// Error de validación de un campo de la configuración
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    /// Nombre del campo como en `config.json`
+    pub field: String,
+    pub message: String,
+}
+
+/// Error de `update_agent_config`: `fields` lista los campos inválidos y
+/// queda vacío si falló el guardado
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigUpdateError {
+    pub message: String,
+    pub fields: Vec<FieldError>,
+}
+
+impl ConfigUpdateError {
+    pub fn from_message(message: String) -> Self {
+        Self { message, fields: Vec::new() }
+    }
+}
+
+/// Etapa del ciclo de vida del agente
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentState {
+    Stopped,
+    Starting,
+    Running,
+    /// Corriendo, pero con algún servicio o cámara caído
+    Degraded,
+    Stopping,
+}
+
+impl AgentState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentState::Stopped => "stopped",
+            AgentState::Starting => "starting",
+            AgentState::Running => "running",
+            AgentState::Degraded => "degraded",
+            AgentState::Stopping => "stopping",
+        }
+    }
+
+    /// `true` si el agente está arriba, aunque sea parcialmente
+    pub fn is_active(&self) -> bool {
+        matches!(self, AgentState::Running | AgentState::Degraded)
+    }
+
+    /// Transiciones permitidas: `Stopped → Starting → Running | Degraded →
+    /// Stopping → Stopped`, más `Starting → Stopped` (rollback) y el paso
+    /// entre `Running` y `Degraded`
+    pub fn can_transition_to(&self, next: AgentState) -> bool {
+        use AgentState::*;
+        matches!(
+            (self, next),
+            (Stopped, Starting)
+                | (Starting, Running)
+                | (Starting, Degraded)
+                | (Starting, Stopped)
+                | (Running, Degraded)
+                | (Degraded, Running)
+                | (Degraded, Degraded)
+                | (Running, Stopping)
+                | (Degraded, Stopping)
+                | (Stopping, Stopped)
+        )
+    }
+}
+
+/// Estado actual del agente y por qué se llegó a él
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentLifecycle {
+    pub state: AgentState,
+    pub reason: String,
+    pub since: DateTime<Utc>,
+}
+
+/// Payload del evento `agent-state-changed`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentStateChange {
+    pub previous: AgentState,
+    pub state: AgentState,
+    pub reason: String,
+    pub at: DateTime<Utc>,
+}
+
+impl AgentLifecycle {
+    pub fn new() -> Self {
+        Self {
+            state: AgentState::Stopped,
+            reason: "Agent not started".to_string(),
+            since: Utc::now(),
+        }
+    }
+
+    /// Pasa a `next` si la transición es válida
+    pub fn transition(&mut self, next: AgentState, reason: impl Into<String>) -> anyhow::Result<AgentStateChange> {
+        if !self.state.can_transition_to(next) {
+            anyhow::bail!("Agent is {}, cannot switch to {}", self.state.as_str(), next.as_str());
+        }
+
+        let change = AgentStateChange {
+            previous: self.state,
+            state: next,
+            reason: reason.into(),
+            at: Utc::now(),
+        };
+        self.state = next;
+        self.reason = change.reason.clone();
+        self.since = change.at;
+        Ok(change)
+    }
+}
+
 /// Estado general del agente
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentStatus {
+    /// `state` es `Running` o `Degraded`
     pub running: bool,
+    pub state: AgentState,
+    pub state_reason: String,
+    pub state_since: DateTime<Utc>,
     pub mediamtx_running: bool,
+    pub mediamtx_status: ProcessStatus,
+    pub mediamtx_restarts: u32,
     pub cloudflared_running: bool,
+    pub cloudflared_status: ProcessStatus,
+    pub tunnel_connected: bool,
     pub cameras_running: u32,
     pub cameras_total: u32,
     pub tunnel_url: Option<String>,
     pub uptime_secs: u64,
+    /// Consumo actual de cada servicio; `None` si no corre o no se pudo leer
+    pub mediamtx_resources: Option<ResourceUsage>,
+    pub cloudflared_resources: Option<ResourceUsage>,
+    pub binaries: Vec<ResolvedBinary>,
+    /// Binarios que no se encontraron en ningún lugar
+    pub missing_binaries: Vec<String>,
+    /// Ver `AppState::config_warnings`
+    pub config_warnings: Vec<String>,
+}
+
+/// Payload del evento `camera-status-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraStatusPayload {
+    pub id: String,
+    pub status: String,
+}
+
+/// Payload del evento `service-status-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatusPayload {
+    pub service: String,
+    pub status: String,
 }
 
 /// Listado de cámaras para serialización
@@ -252,12 +990,39 @@ pub struct CameraInfo {
     pub status: ProcessStatus,
     pub restarts: u32,
     pub last_restart: Option<DateTime<Utc>>,
+    pub next_retry_at: Option<DateTime<Utc>>,
+    pub quarantine: Option<QuarantineInfo>,
+    pub last_failure: Option<FailureInfo>,
+    pub metrics: StreamMetrics,
+    /// Consumo actual de FFmpeg
+    pub resources: Option<ResourceUsage>,
+}
+
+/// Métricas de una cámara con su historial reciente
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraMetrics {
+    pub id: String,
+    pub status: ProcessStatus,
+    pub current: StreamMetrics,
+    pub history: Vec<MetricsSample>,
+    pub resources: Option<ResourceUsage>,
+    pub resource_history: Vec<ResourceUsage>,
+}
+
+/// Consumo de un servicio de soporte con su historial reciente
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceResources {
+    pub service: String,
+    pub current: Option<ResourceUsage>,
+    pub history: Vec<ResourceUsage>,
 }
 
 /// Buffer circular de logs
 pub struct LogBuffer {
     max_lines: usize,
-    logs: HashMap<String, Vec<String>>,
+    logs: HashMap<String, VecDeque<String>>,
 }
 
 impl LogBuffer {
@@ -269,14 +1034,14 @@ impl LogBuffer {
     }
 
     pub fn append(&mut self, component: String, line: String) {
-        let entry = self.logs.entry(component).or_insert_with(Vec::new);
-        entry.push(line);
+        let entry = self.logs.entry(component).or_insert_with(VecDeque::new);
+        entry.push_back(line);
         if entry.len() > self.max_lines {
-            entry.remove(0);
+            entry.pop_front();
         }
     }
 
-    pub fn get(&self, component: &str) -> Option<&Vec<String>> {
+    pub fn get(&self, component: &str) -> Option<&VecDeque<String>> {
         self.logs.get(component)
     }
 
@@ -289,16 +1054,66 @@ impl LogBuffer {
     }
 }
 
+/// Versión actual del esquema de `cameras.json`
+pub const CAMERAS_SCHEMA_VERSION: u32 = 1;
+
+/// Versión actual del esquema de `config.json`
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
 /// Configuración de cámaras persistida
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CamerasConfigFile {
+    /// Los archivos viejos se migran al cargarlos (ver `config_manager`)
+    pub schema_version: u32,
     pub cameras: Vec<CameraConfig>,
 }
 
 impl Default for CamerasConfigFile {
     fn default() -> Self {
         Self {
+            schema_version: CAMERAS_SCHEMA_VERSION,
             cameras: vec![],
         }
     }
 }
+
+/// Formatos de archivo de los agentes anteriores que se pueden importar
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LegacyFormat {
+    /// `cameras.json` de stream-agent-electron: `{ "cameras": [...] }`
+    ElectronCameras,
+    /// `config.json` de stream-agent-electron
+    ElectronConfig,
+    /// `cameras.json` del servidor Node: `{ "cam1": "rtsp://..." }`
+    UrlMap,
+    /// `cameras.json` del servidor Node con registros
+    /// `{ camId: { publicUrl, locationId, ... } }`
+    NodeRegistry,
+}
+
+/// Dato del archivo viejo que no se importó o se importó con cambios
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportIssue {
+    /// Cámara (id en el archivo viejo) a la que se refiere, si aplica
+    pub camera: Option<String>,
+    pub field: String,
+    pub message: String,
+}
+
+/// Resultado (o vista previa) de `import_legacy_config`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyImport {
+    pub cameras_format: Option<LegacyFormat>,
+    pub config_format: Option<LegacyFormat>,
+    /// Cámaras que se agregan
+    pub cameras: Vec<CameraConfig>,
+    /// Configuración resultante; `None` si no se importó ninguna clave
+    pub config: Option<AgentConfig>,
+    pub issues: Vec<ImportIssue>,
+    /// `true` si solo es una vista previa y no se guardó nada
+    pub dry_run: bool,
+}
@@ -0,0 +1,666 @@
+use crate::app_state::*;
+use crate::supervisor::{
+    start_ffmpeg, stop_process, kill_process, is_process_running, parse_progress_line,
+    emit_retry_event, failure_phase, FailureDetector, FailureInfo, FailureReason, FfmpegCapabilities,
+    LogSink, ProcessLauncher, ResourceMonitor, ResourceUsage, RetryPhase, StopOutcome, UnsupportedByFfmpeg,
+};
+use anyhow::Result;
+use chrono::Utc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// Comandos pendientes por cámara antes de que `send` espere
+const COMMAND_BUFFER: usize = 16;
+
+/// Lo necesario para lanzar FFmpeg. Viaja con los comandos que inician la
+/// cámara y la tarea lo conserva para los reinicios automáticos.
+#[derive(Clone)]
+pub struct LaunchContext {
+    pub launcher: Arc<dyn ProcessLauncher>,
+    pub ffmpeg_path: PathBuf,
+    pub sink: LogSink,
+    /// `None` si no se pudo consultar FFmpeg; se asume un build completo
+    pub ffmpeg_capabilities: Option<FfmpegCapabilities>,
+}
+
+/// Comandos que acepta la tarea de una cámara
+enum CameraCommand {
+    Start {
+        launch: LaunchContext,
+        reply: oneshot::Sender<Result<ProcessStatus>>,
+    },
+    Stop {
+        shutdown: ShutdownPolicy,
+        reply: oneshot::Sender<Result<Option<StopOutcome>>>,
+    },
+    Reconnect {
+        launch: LaunchContext,
+        shutdown: ShutdownPolicy,
+        reply: oneshot::Sender<Result<ProcessStatus>>,
+    },
+    Update {
+        config: CameraConfig,
+        reply: oneshot::Sender<()>,
+    },
+    /// Cambió la política global; aplica si la cámara no tiene una propia
+    SetDefaultPolicy {
+        policy: ReconnectPolicy,
+        reply: oneshot::Sender<()>,
+    },
+    /// Saca la cámara de cuarentena y la relanza de inmediato
+    ClearQuarantine {
+        reply: oneshot::Sender<Result<ProcessStatus>>,
+    },
+    /// Chequeo periódico del watchdog: detecta caídas y relanza si corresponde
+    Supervise,
+    /// Relanza FFmpeg de inmediato si la cámara está activa (MediaMTX se recuperó)
+    RestartActive,
+    /// Detiene FFmpeg y termina la tarea
+    Shutdown {
+        shutdown: ShutdownPolicy,
+        reply: oneshot::Sender<Result<Option<StopOutcome>>>,
+    },
+}
+
+/// Handle a la tarea que maneja una cámara.
+///
+/// La tarea es dueña del `CameraRuntime`; el handle solo envía comandos y lee
+/// el último `CameraSnapshot` publicado, así que consultarlo nunca espera a
+/// que FFmpeg arranque o se detenga.
+#[derive(Clone)]
+pub struct CameraHandle {
+    id: String,
+    commands: mpsc::Sender<CameraCommand>,
+    snapshot: watch::Receiver<CameraSnapshot>,
+    metrics: Arc<StdMutex<MetricsRecorder>>,
+    resources: Arc<StdMutex<ResourceMonitor>>,
+}
+
+impl CameraHandle {
+    /// Lanza la tarea de la cámara (detenida hasta recibir `start`)
+    pub fn spawn(config: CameraConfig, default_policy: &ReconnectPolicy, app_handle: Option<tauri::AppHandle>) -> Self {
+        let runtime = CameraRuntime::new(config, default_policy);
+        let id = runtime.config.id.clone();
+        let metrics = runtime.metrics.clone();
+        let resources = runtime.resources.clone();
+
+        let (commands, commands_rx) = mpsc::channel(COMMAND_BUFFER);
+        let (snapshot_tx, snapshot) = watch::channel(CameraSnapshot::from_runtime(&runtime));
+
+        let actor = CameraActor {
+            runtime,
+            default_policy: default_policy.clone(),
+            launch: None,
+            snapshot: snapshot_tx,
+            app_handle,
+        };
+        tokio::spawn(actor.run(commands_rx));
+
+        Self { id, commands, snapshot, metrics, resources }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Último estado publicado por la tarea
+    pub fn snapshot(&self) -> CameraSnapshot {
+        self.snapshot.borrow().clone()
+    }
+
+    pub fn current_metrics(&self) -> StreamMetrics {
+        self.metrics.lock()
+            .map(|m| m.current().clone())
+            .unwrap_or_default()
+    }
+
+    pub fn metrics_history(&self, window_secs: Option<u64>) -> Vec<MetricsSample> {
+        self.metrics.lock()
+            .map(|m| m.history(window_secs))
+            .unwrap_or_default()
+    }
+
+    pub fn current_resources(&self) -> Option<ResourceUsage> {
+        self.resources.lock().ok()
+            .and_then(|r| r.current().cloned())
+    }
+
+    pub fn resource_history(&self, window_secs: Option<u64>) -> Vec<ResourceUsage> {
+        self.resources.lock()
+            .map(|r| r.history(window_secs))
+            .unwrap_or_default()
+    }
+
+    /// Lanza FFmpeg. Si falla, la cámara queda en `Reconnecting` y se reintenta
+    /// en segundo plano; devuelve el estado resultante.
+    pub async fn start(&self, launch: LaunchContext) -> Result<ProcessStatus> {
+        self.request(|reply| CameraCommand::Start { launch, reply }).await?
+    }
+
+    pub async fn stop(&self, shutdown: &ShutdownPolicy) -> Result<Option<StopOutcome>> {
+        let shutdown = shutdown.clone();
+        self.request(|reply| CameraCommand::Stop { shutdown, reply }).await?
+    }
+
+    pub async fn reconnect(&self, launch: LaunchContext, shutdown: &ShutdownPolicy) -> Result<ProcessStatus> {
+        let shutdown = shutdown.clone();
+        self.request(|reply| CameraCommand::Reconnect { launch, shutdown, reply }).await?
+    }
+
+    pub async fn update(&self, config: CameraConfig) -> Result<()> {
+        self.request(|reply| CameraCommand::Update { config, reply }).await
+    }
+
+    pub async fn set_default_policy(&self, policy: ReconnectPolicy) -> Result<()> {
+        self.request(|reply| CameraCommand::SetDefaultPolicy { policy, reply }).await
+    }
+
+    pub async fn clear_quarantine(&self) -> Result<ProcessStatus> {
+        self.request(|reply| CameraCommand::ClearQuarantine { reply }).await?
+    }
+
+    /// Detiene la cámara y termina su tarea
+    pub async fn shutdown(&self, shutdown: &ShutdownPolicy) -> Result<Option<StopOutcome>> {
+        let shutdown = shutdown.clone();
+        self.request(|reply| CameraCommand::Shutdown { shutdown, reply }).await?
+    }
+
+    /// Pide un chequeo de salud sin esperar. Si la cámara está ocupada (por
+    /// ejemplo deteniéndose) el pedido se descarta y se revisa en el próximo tick.
+    pub fn supervise(&self) {
+        self.notify(CameraCommand::Supervise);
+    }
+
+    /// Pide relanzar FFmpeg si la cámara está activa, sin esperar
+    pub fn restart_if_active(&self) {
+        self.notify(CameraCommand::RestartActive);
+    }
+
+    fn notify(&self, command: CameraCommand) {
+        if let Err(e) = self.commands.try_send(command) {
+            log::debug!("Camera {} busy, skipping command: {}", self.id, e);
+        }
+    }
+
+    async fn request<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> CameraCommand) -> Result<T> {
+        let (reply, response) = oneshot::channel();
+        self.commands.send(command(reply)).await
+            .map_err(|_| anyhow::anyhow!("Camera '{}' task is not running", self.id))?;
+        response.await
+            .map_err(|_| anyhow::anyhow!("Camera '{}' task ended before replying", self.id))
+    }
+}
+
+/// Tarea dueña del `CameraRuntime` de una cámara
+struct CameraActor {
+    runtime: CameraRuntime,
+    /// Política global, para cuando la cámara no define la suya
+    default_policy: ReconnectPolicy,
+    launch: Option<LaunchContext>,
+    snapshot: watch::Sender<CameraSnapshot>,
+    app_handle: Option<tauri::AppHandle>,
+}
+
+impl CameraActor {
+    async fn run(mut self, mut commands: mpsc::Receiver<CameraCommand>) {
+        while let Some(command) = commands.recv().await {
+            // Publicar antes de responder, así quien espera la respuesta ya ve el estado nuevo
+            match command {
+                CameraCommand::Start { launch, reply } => {
+                    let result = self.start(launch);
+                    self.publish();
+                    let _ = reply.send(result);
+                }
+                CameraCommand::Stop { shutdown, reply } => {
+                    let result = self.stop(&shutdown).await;
+                    self.publish();
+                    let _ = reply.send(result);
+                }
+                CameraCommand::Reconnect { launch, shutdown, reply } => {
+                    let result = self.reconnect(launch, &shutdown).await;
+                    self.publish();
+                    let _ = reply.send(result);
+                }
+                CameraCommand::Update { config, reply } => {
+                    self.update(config);
+                    self.publish();
+                    let _ = reply.send(());
+                }
+                CameraCommand::SetDefaultPolicy { policy, reply } => {
+                    self.default_policy = policy;
+                    self.runtime.reconnect_policy = self.runtime.config.effective_reconnect_policy(&self.default_policy);
+                    let _ = reply.send(());
+                }
+                CameraCommand::ClearQuarantine { reply } => {
+                    let result = self.clear_quarantine();
+                    self.publish();
+                    let _ = reply.send(result);
+                }
+                CameraCommand::Supervise => {
+                    self.check_health();
+                    self.restart_if_due();
+                    self.publish();
+                }
+                CameraCommand::RestartActive => {
+                    self.restart_active();
+                    self.publish();
+                }
+                CameraCommand::Shutdown { shutdown, reply } => {
+                    let result = self.stop(&shutdown).await;
+                    self.publish();
+                    let _ = reply.send(result);
+                    break;
+                }
+            }
+        }
+
+        // Si se soltaron todos los handles sin Shutdown, no dejar FFmpeg huérfano
+        if let Some(handle) = &mut self.runtime.process {
+            if let Err(e) = kill_process(handle) {
+                log::error!("Failed to kill FFmpeg for camera {}: {}", self.runtime.config.id, e);
+            }
+        }
+        log::debug!("Camera {} task ended", self.runtime.config.id);
+    }
+
+    /// Publica el snapshot y avisa a la UI si cambió el estado
+    fn publish(&mut self) {
+        let snapshot = CameraSnapshot::from_runtime(&self.runtime);
+        let previous = self.snapshot.send_replace(snapshot);
+
+        if previous.status != self.runtime.status {
+            if let Some(app_handle) = &self.app_handle {
+                let payload = CameraStatusPayload {
+                    id: self.runtime.config.id.clone(),
+                    status: self.runtime.status.as_str().to_string(),
+                };
+                if let Err(e) = app_handle.emit("camera-status-changed", payload) {
+                    log::warn!("Failed to emit camera-status-changed: {}", e);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, launch: LaunchContext) -> Result<ProcessStatus> {
+        let id = self.runtime.config.id.clone();
+
+        if !self.runtime.config.enabled {
+            return Err(anyhow::anyhow!("Camera '{}' is disabled", id));
+        }
+
+        if self.runtime.status == ProcessStatus::Running {
+            return Err(anyhow::anyhow!("Camera '{}' is already running", id));
+        }
+
+        log::info!("Starting camera: {}", id);
+
+        // Un inicio manual arranca con el presupuesto de reintentos completo
+        self.runtime.stats.consecutive_failures = 0;
+        self.runtime.stats.last_delay_ms = None;
+        self.runtime.stats.cancel_retry();
+        self.runtime.stats.reset_quarantine();
+        self.runtime.last_failure = None;
+        self.launch = Some(launch);
+
+        if let Err(e) = self.spawn_ffmpeg() {
+            log::warn!("Camera {} failed to start: {}", id, e);
+            let failure = self.conclude_failure(Some(&e));
+            self.schedule_restart(failure);
+            if self.runtime.status == ProcessStatus::Failed {
+                return Err(e);
+            }
+            return Ok(self.runtime.status.clone());
+        }
+
+        log::info!("Camera {} started successfully", id);
+        Ok(ProcessStatus::Running)
+    }
+
+    /// Detiene FFmpeg. Devuelve en qué etapa terminó el proceso, o `None` si
+    /// no había proceso que detener.
+    async fn stop(&mut self, shutdown: &ShutdownPolicy) -> Result<Option<StopOutcome>> {
+        let id = self.runtime.config.id.clone();
+
+        // Si ya está detenida, simplemente retornar Ok (no es error)
+        if self.runtime.status == ProcessStatus::Stopped {
+            log::debug!("Camera '{}' is already stopped, skipping", id);
+            return Ok(None);
+        }
+
+        log::info!("Stopping camera: {}", id);
+
+        if matches!(self.runtime.status, ProcessStatus::Reconnecting | ProcessStatus::Quarantined) {
+            self.emit_retry(RetryPhase::Cancelled);
+        }
+
+        let mut outcome = None;
+        if let Some(handle) = &mut self.runtime.process {
+            outcome = Some(stop_process(handle, shutdown).await?);
+            self.runtime.stats.total_uptime_secs += handle.uptime().as_secs();
+        }
+
+        self.runtime.process = None;
+        self.clear_resources();
+        self.runtime.status = ProcessStatus::Stopped;
+        self.runtime.stats.cancel_retry();
+        self.runtime.stats.reset_quarantine();
+
+        log::info!("Camera {} stopped successfully", id);
+        Ok(outcome)
+    }
+
+    async fn reconnect(&mut self, launch: LaunchContext, shutdown: &ShutdownPolicy) -> Result<ProcessStatus> {
+        let id = self.runtime.config.id.clone();
+        log::info!("Reconnecting camera: {}", id);
+
+        // Detener si está corriendo
+        match self.stop(shutdown).await {
+            Ok(_) => log::info!("Camera {} stopped for reconnection", id),
+            Err(e) => log::warn!("Camera {} did not stop cleanly: {}", id, e),
+        }
+
+        // Pequeño delay antes de reiniciar
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        self.start(launch)
+    }
+
+    fn update(&mut self, config: CameraConfig) {
+        let id = self.runtime.config.id.clone();
+        log::info!("Updating camera: {}", id);
+
+        // Si está corriendo, necesita reinicio
+        let was_running = self.runtime.status == ProcessStatus::Running;
+
+        // La política nueva rige desde el próximo fallo
+        self.runtime.reconnect_policy = config.effective_reconnect_policy(&self.default_policy);
+        self.runtime.config = config;
+
+        if was_running {
+            log::warn!("Camera {} was running, requires restart for changes to take effect", id);
+        }
+    }
+
+    /// Lanza FFmpeg con el `LaunchContext` guardado y marca la cámara `Running`
+    fn spawn_ffmpeg(&mut self) -> Result<()> {
+        let launch = self.launch.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Camera '{}' was never started", self.runtime.config.id))?;
+        let sink = camera_sink(&self.runtime, &launch.sink);
+
+        let handle = start_ffmpeg(
+            launch.launcher.as_ref(),
+            &launch.ffmpeg_path,
+            &self.runtime.config.id,
+            &self.runtime.config.rtsp_url,
+            &self.runtime.config.encoding,
+            &self.runtime.config.quality,
+            &self.runtime.config.audio_mode,
+            launch.ffmpeg_capabilities.as_ref(),
+            &sink,
+        )?;
+
+        self.runtime.process = Some(handle);
+        self.clear_resources();
+        self.runtime.status = ProcessStatus::Running;
+        self.runtime.stats.cancel_retry();
+        Ok(())
+    }
+
+    fn sample_resources(&self) {
+        let Some(pid) = self.runtime.process.as_ref().and_then(|p| p.pid) else {
+            return;
+        };
+        if let Ok(mut resources) = self.runtime.resources.lock() {
+            resources.sample(pid);
+        }
+    }
+
+    fn clear_resources(&self) {
+        if let Ok(mut resources) = self.runtime.resources.lock() {
+            resources.clear();
+        }
+    }
+
+    /// Detecta si FFmpeg terminó inesperadamente, o si sigue vivo pero sin
+    /// avanzar frames durante `stall_timeout_ms`. En ambos casos la cámara pasa
+    /// a `Reconnecting` con el próximo intento agendado según su
+    /// `ReconnectPolicy` (o a `Failed` si no quedan reintentos).
+    fn check_health(&mut self) {
+        if self.runtime.status != ProcessStatus::Running {
+            return;
+        }
+
+        let id = self.runtime.config.id.clone();
+        let Some(handle) = &mut self.runtime.process else {
+            return;
+        };
+        let alive = is_process_running(handle);
+        let uptime_ms = handle.uptime().as_millis() as u64;
+
+        if alive {
+            if let Some(stalled_ms) = stalled_for(&self.runtime) {
+                log::warn!("Camera {} stalled: no new frames for {}ms, killing FFmpeg", id, stalled_ms);
+                if let Some(handle) = &mut self.runtime.process {
+                    if let Err(e) = kill_process(handle) {
+                        log::error!("Failed to kill stalled FFmpeg for camera {}: {}", id, e);
+                    }
+                }
+                let mut failure = self.conclude_failure(None);
+                failure.message.get_or_insert_with(|| format!("no new frames for {}ms", stalled_ms));
+                self.schedule_restart(failure);
+                return;
+            }
+
+            // Resetear contador de fallos tras un período estable
+            if self.runtime.stats.reset_if_stable(uptime_ms, &self.runtime.reconnect_policy) {
+                log::info!("Camera {} stable for {}ms, resetting failure counter", id, uptime_ms);
+                self.runtime.last_failure = None;
+            }
+            self.sample_resources();
+            return;
+        }
+
+        log::warn!("Camera {} process died unexpectedly", id);
+        let failure = self.conclude_failure(None);
+        self.schedule_restart(failure);
+    }
+
+    /// Re-lanza FFmpeg si la cámara está en `Reconnecting` o `Quarantined` y
+    /// el intento ya venció
+    fn restart_if_due(&mut self) {
+        let waiting = matches!(self.runtime.status, ProcessStatus::Reconnecting | ProcessStatus::Quarantined);
+        if !waiting || !self.runtime.stats.is_retry_due() {
+            return;
+        }
+
+        let id = self.runtime.config.id.clone();
+        if self.runtime.status == ProcessStatus::Quarantined {
+            log::info!("Camera {} quarantine cooldown ended, trying once more", id);
+            self.runtime.stats.begin_probation();
+        }
+        log::info!(
+            "Restarting camera {} (attempt {}/{})",
+            id, self.runtime.stats.consecutive_failures, self.runtime.reconnect_policy.max_retries
+        );
+
+        self.emit_retry(RetryPhase::Attempting);
+        match self.spawn_ffmpeg() {
+            Ok(()) => {
+                log::info!("Camera {} restarted by watchdog", id);
+                self.emit_retry(RetryPhase::Recovered);
+            }
+            Err(e) => {
+                log::warn!("Camera {} restart failed: {}", id, e);
+                let failure = self.conclude_failure(Some(&e));
+                self.schedule_restart(failure);
+            }
+        }
+    }
+
+    /// Saca la cámara de cuarentena a pedido del operador y la relanza ya
+    fn clear_quarantine(&mut self) -> Result<ProcessStatus> {
+        let id = self.runtime.config.id.clone();
+        if self.runtime.status != ProcessStatus::Quarantined {
+            return Err(anyhow::anyhow!("Camera '{}' is not quarantined", id));
+        }
+
+        log::info!("Quarantine cleared for camera {}", id);
+        self.runtime.stats.reset_quarantine();
+        self.runtime.stats.consecutive_failures = 0;
+        self.runtime.stats.last_delay_ms = None;
+        self.runtime.stats.schedule_retry(0);
+        self.runtime.status = ProcessStatus::Reconnecting;
+
+        self.restart_if_due();
+        Ok(self.runtime.status.clone())
+    }
+
+    /// Relanza FFmpeg de inmediato si la cámara está `Running` o `Reconnecting`
+    /// (las que están en cuarentena esperan su cooldown)
+    fn restart_active(&mut self) {
+        if self.runtime.status != ProcessStatus::Running && self.runtime.status != ProcessStatus::Reconnecting {
+            return;
+        }
+
+        let id = self.runtime.config.id.clone();
+        if let Some(handle) = &mut self.runtime.process {
+            if let Err(e) = kill_process(handle) {
+                log::error!("Failed to kill FFmpeg for camera {}: {}", id, e);
+            }
+            self.runtime.stats.total_uptime_secs += handle.uptime().as_secs();
+            self.runtime.process = None;
+        }
+
+        log::info!("Restarting camera {} after MediaMTX recovery", id);
+        if let Err(e) = self.spawn_ffmpeg() {
+            log::warn!("Camera {} restart failed: {}", id, e);
+            let failure = self.conclude_failure(Some(&e));
+            self.schedule_restart(failure);
+        }
+    }
+
+    /// Clasifica la caída del proceso actual a partir de lo visto en su stderr.
+    /// `error` es el error del lanzamiento, si el proceso ni siquiera arrancó.
+    fn conclude_failure(&self, error: Option<&anyhow::Error>) -> FailureInfo {
+        let exit_status = self.runtime.process.as_ref()
+            .and_then(|p| p.exit_status)
+            .map(|status| status.to_string());
+        let mut failure = self.runtime.failure_detector.lock()
+            .map(|detector| detector.conclude(exit_status.clone()))
+            .unwrap_or_else(|_| FailureDetector::default().conclude(exit_status));
+        if failure.message.is_none() {
+            failure.message = error.map(|e| format!("{:#}", e));
+        }
+        // Pedir algo que el FFmpeg local no tiene falla igual en cada intento
+        if error.map_or(false, |e| e.downcast_ref::<UnsupportedByFfmpeg>().is_some()) {
+            failure.reason = FailureReason::UnsupportedCodec;
+        }
+        failure
+    }
+
+    /// Descarta el proceso actual, registra el reinicio y agenda el próximo
+    /// intento. Si el motivo no se arregla reintentando, la cámara queda `Failed`.
+    fn schedule_restart(&mut self, failure: FailureInfo) {
+        if let Some(handle) = self.runtime.process.take() {
+            self.runtime.stats.total_uptime_secs += handle.uptime().as_secs();
+        }
+        self.clear_resources();
+        let runtime = &mut self.runtime;
+
+        let retryable = failure.reason.is_retryable();
+        log::warn!(
+            "Camera {} failure: {:?}{}",
+            runtime.config.id,
+            failure.reason,
+            failure.message.as_deref().map(|m| format!(" ({})", m)).unwrap_or_default()
+        );
+        runtime.last_failure = Some(failure);
+
+        runtime.status = if retryable {
+            runtime.stats.record_failure(&runtime.reconnect_policy)
+        } else {
+            runtime.stats.record_fatal_failure()
+        };
+        match runtime.status {
+            ProcessStatus::Failed if !retryable => log::error!(
+                "Camera {} will not be restarted: fix its configuration and start it again",
+                runtime.config.id
+            ),
+            ProcessStatus::Failed => log::error!(
+                "Camera {} will not be restarted ({} consecutive failures)",
+                runtime.config.id, runtime.stats.consecutive_failures
+            ),
+            ProcessStatus::Quarantined => log::warn!(
+                "Camera {} quarantined until {:?}: {}",
+                runtime.config.id,
+                runtime.stats.next_retry_at,
+                runtime.stats.quarantine.as_ref().map_or("", |q| q.reason.as_str())
+            ),
+            _ => log::info!(
+                "Camera {} scheduled for restart in {:?}ms (attempt {}/{})",
+                runtime.config.id, runtime.stats.last_delay_ms, runtime.stats.consecutive_failures,
+                runtime.reconnect_policy.max_retries
+            ),
+        }
+        self.emit_retry(failure_phase(&self.runtime.status));
+    }
+
+    fn emit_retry(&self, phase: RetryPhase) {
+        emit_retry_event(
+            self.app_handle.as_ref(),
+            &self.runtime.config.id,
+            phase,
+            &self.runtime.stats,
+            &self.runtime.reconnect_policy,
+        );
+    }
+}
+
+/// Prepara el sink de logs de una cámara: las líneas de progreso de FFmpeg
+/// actualizan sus métricas en lugar de llenar el buffer de logs.
+/// Las demás líneas se revisan en busca de errores conocidos.
+fn camera_sink(runtime: &CameraRuntime, sink: &LogSink) -> LogSink {
+    if let Ok(mut metrics) = runtime.metrics.lock() {
+        metrics.reset();
+    }
+    if let Ok(mut detector) = runtime.failure_detector.lock() {
+        detector.reset();
+    }
+
+    let metrics = runtime.metrics.clone();
+    let detector = runtime.failure_detector.clone();
+    sink.with_line_handler(move |line| {
+        let Some(progress) = parse_progress_line(line) else {
+            if let Ok(mut detector) = detector.lock() {
+                detector.observe(line);
+            }
+            return false;
+        };
+        if let Ok(mut metrics) = metrics.lock() {
+            metrics.record(&progress);
+        }
+        true
+    })
+}
+
+/// Milisegundos que la cámara lleva sin avanzar frames, si supera su `stall_timeout_ms`
+fn stalled_for(runtime: &CameraRuntime) -> Option<u64> {
+    let timeout_ms = runtime.reconnect_policy.stall_timeout_ms;
+    if timeout_ms == 0 {
+        return None;
+    }
+
+    let handle = runtime.process.as_ref()?;
+    let uptime_ms = handle.uptime().as_millis() as u64;
+
+    // Sin frames todavía: contar desde el arranque del proceso
+    let idle_ms = match runtime.current_metrics().last_frame_at {
+        Some(at) => (Utc::now().signed_duration_since(at).num_milliseconds().max(0) as u64).min(uptime_ms),
+        None => uptime_ms,
+    };
+
+    (idle_ms >= timeout_ms).then_some(idle_ms)
+}
@@ -1,14 +1,39 @@
 use crate::app_state::*;
-use crate::supervisor::{start_ffmpeg, stop_process, is_process_running, reconnect_with_backoff};
+use crate::cameras::camera_actor::{CameraHandle, LaunchContext};
+use crate::config_manager::{self, NewerSchemaVersion, CAMERAS_MIGRATIONS};
+use crate::supervisor::StopOutcome;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::Path;
+use tokio::sync::Mutex;
 
-/// Agrega una nueva cámara a la configuración
-pub fn add_camera(
-    cameras: &mut HashMap<String, CameraRuntime>,
+// Las funciones de este módulo sostienen el lock del mapa solo para buscar o
+// modificar handles; el trabajo sobre FFmpeg lo hace la tarea de cada cámara.
+
+/// Busca el handle de una cámara
+pub async fn get_camera(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
+    id: &str,
+) -> Result<CameraHandle> {
+    cameras.lock().await
+        .get(id)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Camera '{}' not found", id))
+}
+
+/// Agrega una nueva cámara y lanza su tarea (detenida)
+pub async fn add_camera(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     config: CameraConfig,
+    default_policy: &ReconnectPolicy,
+    app_handle: Option<tauri::AppHandle>,
 ) -> Result<()> {
+    if let Some(policy) = &config.reconnect {
+        policy.validate()
+            .with_context(|| format!("Invalid reconnect policy for camera '{}'", config.id))?;
+    }
+
+    let mut cameras = cameras.lock().await;
     let id = config.id.clone();
     
     if cameras.contains_key(&id) {
@@ -16,275 +41,383 @@ pub fn add_camera(
     }
     
     log::info!("Adding camera: {} ({})", config.name, id);
-    let runtime = CameraRuntime::new(config);
-    cameras.insert(id, runtime);
+    cameras.insert(id, CameraHandle::spawn(config, default_policy, app_handle));
     
     Ok(())
 }
 
+/// Agrega varias cámaras con el mapa ya bloqueado. Primero valida todas: si
+/// alguna tiene un id repetido o una política inválida no se agrega ninguna.
+pub fn insert_cameras(
+    cameras: &mut HashMap<String, CameraHandle>,
+    configs: &[CameraConfig],
+    default_policy: &ReconnectPolicy,
+    app_handle: Option<tauri::AppHandle>,
+) -> Result<()> {
+    for (index, config) in configs.iter().enumerate() {
+        if let Some(policy) = &config.reconnect {
+            policy.validate()
+                .with_context(|| format!("Invalid reconnect policy for camera '{}'", config.id))?;
+        }
+        if cameras.contains_key(&config.id) || configs[..index].iter().any(|c| c.id == config.id) {
+            return Err(anyhow::anyhow!("Camera with id '{}' already exists", config.id));
+        }
+    }
+
+    for config in configs {
+        log::info!("Adding camera: {} ({})", config.name, config.id);
+        cameras.insert(config.id.clone(), CameraHandle::spawn(config.clone(), default_policy, app_handle.clone()));
+    }
+    Ok(())
+}
+
 /// Actualiza la configuración de una cámara existente
-pub fn update_camera(
-    cameras: &mut HashMap<String, CameraRuntime>,
+pub async fn update_camera(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     id: &str,
     updated_config: CameraConfig,
 ) -> Result<()> {
-    let runtime = cameras.get_mut(id)
-        .ok_or_else(|| anyhow::anyhow!("Camera '{}' not found", id))?;
-    
-    log::info!("Updating camera: {}", id);
-    
-    // Si está corriendo, necesita reinicio
-    let was_running = runtime.status == ProcessStatus::Running;
-    
-    runtime.config = updated_config;
-    
-    if was_running {
-        log::warn!("Camera {} was running, requires restart for changes to take effect", id);
+    if let Some(policy) = &updated_config.reconnect {
+        policy.validate()
+            .with_context(|| format!("Invalid reconnect policy for camera '{}'", id))?;
     }
-    
-    Ok(())
+
+    get_camera(cameras, id).await?
+        .update(updated_config).await
 }
 
-/// Elimina una cámara
-pub fn remove_camera(
-    cameras: &mut HashMap<String, CameraRuntime>,
+/// Elimina una cámara, deteniendo su proceso y su tarea
+pub async fn remove_camera(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     id: &str,
+    shutdown: &ShutdownPolicy,
 ) -> Result<()> {
-    let mut runtime = cameras.remove(id)
+    let camera = cameras.lock().await
+        .remove(id)
         .ok_or_else(|| anyhow::anyhow!("Camera '{}' not found", id))?;
     
     log::info!("Removing camera: {}", id);
-    
-    // Detener proceso si está corriendo
-    if let Some(handle) = &mut runtime.process {
-        stop_process(handle)?;
-    }
+    camera.shutdown(shutdown).await?;
     
     Ok(())
 }
 
-/// Inicia el proceso FFmpeg de una cámara
+/// Inicia el proceso FFmpeg de una cámara. Devuelve el estado resultante:
+/// `Running`, o `Reconnecting` si el primer intento falló y se reintenta.
 pub async fn start_camera(
-    cameras: &mut HashMap<String, CameraRuntime>,
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     id: &str,
-    ffmpeg_path: &Path,
-) -> Result<()> {
-    let runtime = cameras.get_mut(id)
-        .ok_or_else(|| anyhow::anyhow!("Camera '{}' not found", id))?;
-    
-    if !runtime.config.enabled {
-        return Err(anyhow::anyhow!("Camera '{}' is disabled", id));
-    }
-    
-    if runtime.status == ProcessStatus::Running {
-        return Err(anyhow::anyhow!("Camera '{}' is already running", id));
-    }
-    
-    log::info!("Starting camera: {}", id);
-    
-    let camera_id = runtime.config.id.clone();
-    let rtsp_url = runtime.config.rtsp_url.clone();
-    let encoding = runtime.config.encoding.clone();
-    let quality = runtime.config.quality.clone();
-    let audio_mode = runtime.config.audio_mode.clone();
-    let ffmpeg_path = ffmpeg_path.to_path_buf();
-    
-    // Usar reconexión con backoff
-    let policy = runtime.reconnect_policy.clone();
-    let stats = &mut runtime.stats;
-    let status = &mut runtime.status;
-    
-    let handle = reconnect_with_backoff(
-        &format!("Camera {}", id),
-        &policy,
-        stats,
-        || {
-            let ffmpeg_path = ffmpeg_path.clone();
-            let camera_id = camera_id.clone();
-            let rtsp_url = rtsp_url.clone();
-            let encoding = encoding.clone();
-            let quality = quality.clone();
-            let audio_mode = audio_mode.clone();
-            
-            async move {
-                start_ffmpeg(
-                    &ffmpeg_path,
-                    &camera_id,
-                    &rtsp_url,
-                    &encoding,
-                    &quality,
-                    &audio_mode,
-                )
-            }
-        }
-    ).await?;
-    
-    runtime.process = Some(handle);
-    runtime.status = ProcessStatus::Running;
-    
-    log::info!("Camera {} started successfully", id);
-    Ok(())
+    launch: LaunchContext,
+) -> Result<ProcessStatus> {
+    get_camera(cameras, id).await?
+        .start(launch).await
 }
 
-/// Detiene el proceso FFmpeg de una cámara
-pub fn stop_camera(
-    cameras: &mut HashMap<String, CameraRuntime>,
+/// Detiene el proceso FFmpeg de una cámara. Devuelve en qué etapa terminó
+/// el proceso, o `None` si no había proceso que detener.
+pub async fn stop_camera(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     id: &str,
-) -> Result<()> {
-    let runtime = cameras.get_mut(id)
-        .ok_or_else(|| anyhow::anyhow!("Camera '{}' not found", id))?;
-    
-    // Si ya está detenida, simplemente retornar Ok (no es error)
-    if runtime.status == ProcessStatus::Stopped {
-        log::debug!("Camera '{}' is already stopped, skipping", id);
-        return Ok(());
-    }
-    
-    log::info!("Stopping camera: {}", id);
-    
-    if let Some(handle) = &mut runtime.process {
-        stop_process(handle)?;
-    }
-    
-    runtime.process = None;
-    runtime.status = ProcessStatus::Stopped;
-    
-    log::info!("Camera {} stopped successfully", id);
-    Ok(())
+    shutdown: &ShutdownPolicy,
+) -> Result<Option<StopOutcome>> {
+    get_camera(cameras, id).await?
+        .stop(shutdown).await
 }
 
 /// Reinicia una cámara (stop + start)
 pub async fn reconnect_camera(
-    cameras: &mut HashMap<String, CameraRuntime>,
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
+    id: &str,
+    launch: LaunchContext,
+    shutdown: &ShutdownPolicy,
+) -> Result<ProcessStatus> {
+    get_camera(cameras, id).await?
+        .reconnect(launch, shutdown).await
+}
+
+/// Saca una cámara de cuarentena y la relanza. Devuelve el estado resultante.
+pub async fn clear_camera_quarantine(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
+    id: &str,
+) -> Result<ProcessStatus> {
+    get_camera(cameras, id).await?
+        .clear_quarantine().await
+}
+
+/// Cambia la política propia de una cámara (`None` vuelve a la global)
+pub async fn set_camera_reconnect_policy(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     id: &str,
-    ffmpeg_path: &Path,
+    policy: Option<ReconnectPolicy>,
 ) -> Result<()> {
-    log::info!("Reconnecting camera: {}", id);
-    
-    // Detener si está corriendo
-    if let Ok(_) = stop_camera(cameras, id) {
-        log::info!("Camera {} stopped for reconnection", id);
+    let camera = get_camera(cameras, id).await?;
+    let mut config = camera.snapshot().config;
+    config.reconnect = policy;
+    update_camera(cameras, id, config).await
+}
+
+/// Aplica una nueva política global a las cámaras que no tienen una propia
+pub async fn set_default_reconnect_policy(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
+    policy: &ReconnectPolicy,
+) {
+    let handles: Vec<CameraHandle> = cameras.lock().await.values().cloned().collect();
+    for camera in handles {
+        if let Err(e) = camera.set_default_policy(policy.clone()).await {
+            log::warn!("Failed to update reconnect policy of camera {}: {}", camera.id(), e);
+        }
     }
-    
-    // Pequeño delay antes de reiniciar
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    
-    // Iniciar nuevamente
-    start_camera(cameras, id, ffmpeg_path).await?;
-    
-    log::info!("Camera {} reconnected successfully", id);
-    Ok(())
 }
 
 /// Lista todas las cámaras con su información
-pub fn list_cameras(cameras: &HashMap<String, CameraRuntime>) -> Vec<CameraInfo> {
-    cameras.iter().map(|(_, runtime)| {
+pub fn list_cameras(cameras: &HashMap<String, CameraHandle>) -> Vec<CameraInfo> {
+    cameras.values().map(|camera| {
+        let snapshot = camera.snapshot();
         CameraInfo {
-            id: runtime.config.id.clone(),
-            name: runtime.config.name.clone(),
-            rtsp_url: runtime.config.rtsp_url.clone(),
-            enabled: runtime.config.enabled,
-            encoding: runtime.config.encoding.clone(),
-            quality: runtime.config.quality.clone(),
-            audio_mode: runtime.config.audio_mode.clone(),
-            status: runtime.status.clone(),
-            restarts: runtime.stats.restarts,
-            last_restart: runtime.stats.last_restart,
+            id: snapshot.config.id,
+            name: snapshot.config.name,
+            rtsp_url: snapshot.config.rtsp_url,
+            enabled: snapshot.config.enabled,
+            encoding: snapshot.config.encoding,
+            quality: snapshot.config.quality,
+            audio_mode: snapshot.config.audio_mode,
+            status: snapshot.status,
+            restarts: snapshot.stats.restarts,
+            last_restart: snapshot.stats.last_restart,
+            next_retry_at: snapshot.stats.next_retry_at,
+            quarantine: snapshot.stats.quarantine,
+            last_failure: snapshot.last_failure,
+            metrics: camera.current_metrics(),
+            resources: camera.current_resources(),
         }
     }).collect()
 }
 
-/// Verifica el estado de todos los procesos de cámaras
-pub fn check_cameras_health(cameras: &mut HashMap<String, CameraRuntime>) {
-    for (id, runtime) in cameras.iter_mut() {
-        if runtime.status == ProcessStatus::Running {
-            if let Some(handle) = &mut runtime.process {
-                if !is_process_running(handle) {
-                    log::warn!("Camera {} process died unexpectedly", id);
-                    runtime.status = ProcessStatus::Failed;
-                    runtime.process = None;
-                }
-            }
-        }
+/// Obtiene las métricas de una cámara con el historial de los últimos `window_secs`
+pub fn get_camera_metrics(
+    cameras: &HashMap<String, CameraHandle>,
+    id: &str,
+    window_secs: Option<u64>,
+) -> Result<CameraMetrics> {
+    let camera = cameras.get(id)
+        .ok_or_else(|| anyhow::anyhow!("Camera '{}' not found", id))?;
+
+    Ok(CameraMetrics {
+        id: id.to_string(),
+        status: camera.snapshot().status,
+        current: camera.current_metrics(),
+        history: camera.metrics_history(window_secs),
+        resources: camera.current_resources(),
+        resource_history: camera.resource_history(window_secs),
+    })
+}
+
+/// Pide a cada cámara un chequeo de salud (caídas, cuelgues y reintentos
+/// vencidos). No espera a que terminen.
+pub fn supervise_cameras(cameras: &HashMap<String, CameraHandle>) {
+    for camera in cameras.values() {
+        camera.supervise();
     }
 }
 
-/// Detiene todas las cámaras en ejecución
-pub fn stop_all_cameras(cameras: &mut HashMap<String, CameraRuntime>) -> Result<()> {
+/// Pide relanzar de inmediato todas las cámaras activas (`Running` o
+/// `Reconnecting`), por ejemplo después de que MediaMTX se recuperó de una caída.
+pub fn restart_active_cameras(cameras: &HashMap<String, CameraHandle>) {
+    for camera in cameras.values() {
+        camera.restart_if_active();
+    }
+}
+
+/// Detiene todas las cámaras en ejecución, en paralelo
+pub async fn stop_all_cameras(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
+    shutdown: &ShutdownPolicy,
+) -> Result<()> {
     log::info!("Stopping all cameras");
     
-    let camera_ids: Vec<String> = cameras.keys().cloned().collect();
+    let handles: Vec<CameraHandle> = cameras.lock().await.values().cloned().collect();
+    stop_handles(handles, shutdown, false).await;
     
-    for id in camera_ids {
-        if let Err(e) = stop_camera(cameras, &id) {
-            log::error!("Failed to stop camera {}: {}", id, e);
+    Ok(())
+}
+
+/// Detiene todas las cámaras, termina sus tareas y vacía el mapa
+pub async fn remove_all_cameras(
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
+    shutdown: &ShutdownPolicy,
+) {
+    let handles: Vec<CameraHandle> = cameras.lock().await.drain().map(|(_, camera)| camera).collect();
+    stop_handles(handles, shutdown, true).await;
+}
+
+async fn stop_handles(handles: Vec<CameraHandle>, shutdown: &ShutdownPolicy, end_tasks: bool) {
+    let tasks: Vec<_> = handles.into_iter().map(|camera| {
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let result = if end_tasks {
+                camera.shutdown(&shutdown).await
+            } else {
+                camera.stop(&shutdown).await
+            };
+            if let Err(e) = result {
+                log::error!("Failed to stop camera {}: {}", camera.id(), e);
+            }
+        })
+    }).collect();
+    
+    for task in tasks {
+        if let Err(e) = task.await {
+            log::error!("Camera stop task ended abnormally: {}", e);
         }
     }
-    
-    Ok(())
 }
 
-/// Carga configuración de cámaras desde archivo
-pub fn load_cameras_config(config_path: &Path) -> Result<Vec<CameraConfig>> {
+/// Cámaras leídas de `cameras.json`
+#[derive(Debug)]
+pub struct LoadedCameras {
+    pub cameras: Vec<CameraConfig>,
+    /// Aviso si el archivo estaba dañado y se restauró desde un backup
+    pub warning: Option<String>,
+}
+
+/// Carga configuración de cámaras desde archivo. Si el archivo no se puede
+/// interpretar (ej. quedó truncado por un corte de luz) se restaura el
+/// backup válido más reciente y se devuelve un aviso; el archivo dañado se
+/// conserva como `cameras.json.corrupt`. Un archivo de una versión más nueva
+/// del agente no está dañado: se devuelve el error sin tocar nada.
+pub fn load_cameras_config(config_path: &Path) -> Result<LoadedCameras> {
     if !config_path.exists() {
         log::warn!("Cameras config file not found: {:?}", config_path);
-        return Ok(Vec::new());
+        return Ok(LoadedCameras { cameras: Vec::new(), warning: None });
     }
     
-    let content = std::fs::read_to_string(config_path)
+    let content = std::fs::read(config_path)
         .context("Failed to read cameras config")?;
     
-    let config_file: CamerasConfigFile = serde_json::from_str(&content)
+    // Solo un archivo que no se puede interpretar cuenta como dañado; los
+    // errores al reescribirlo migrado se devuelven tal cual
+    let error = match parse_cameras_config(&content) {
+        Ok(_) => {
+            let cameras = read_cameras_config(config_path, &content)?;
+            return Ok(LoadedCameras { cameras, warning: None });
+        }
+        Err(e) if e.downcast_ref::<NewerSchemaVersion>().is_some() => return Err(e),
+        Err(e) => e,
+    };
+    log::error!("Cameras config is damaged: {:#}", error);
+    
+    for backup in config_manager::list_backups(config_path, config_manager::CAMERAS_BACKUPS) {
+        let Ok(backup_content) = std::fs::read(&backup) else { continue };
+        // Validar el backup antes de tocar el archivo principal
+        if let Err(e) = parse_cameras_config(&backup_content) {
+            log::warn!("Skipping unusable backup {:?}: {:#}", backup, e);
+            continue;
+        }
+        
+        let corrupt_path = config_path.with_extension("json.corrupt");
+        std::fs::write(&corrupt_path, &content)
+            .context("Failed to keep damaged cameras config")?;
+        config_manager::write_atomic(config_path, &backup_content)
+            .context("Failed to restore cameras config from backup")?;
+        let cameras = read_cameras_config(config_path, &backup_content)?;
+        
+        let warning = format!(
+            "cameras.json was damaged ({}) and was restored from {}; the damaged file was kept as {}",
+            error.root_cause(),
+            backup.file_name().unwrap_or_default().to_string_lossy(),
+            corrupt_path.file_name().unwrap_or_default().to_string_lossy(),
+        );
+        log::warn!("{}", warning);
+        return Ok(LoadedCameras { cameras, warning: Some(warning) });
+    }
+    
+    Err(error.context("No valid backup of cameras config to restore"))
+}
+
+/// Interpreta `cameras.json` migrándolo (y reescribiéndolo) si es de una
+/// versión anterior
+fn read_cameras_config(config_path: &Path, content: &[u8]) -> Result<Vec<CameraConfig>> {
+    let content = std::str::from_utf8(content)
+        .context("Cameras config is not valid UTF-8")?;
+    let migrated = config_manager::migrate_file(config_path, content, CAMERAS_MIGRATIONS)?;
+    let mut config_file: CamerasConfigFile = serde_json::from_value(migrated)
         .context("Failed to parse cameras config")?;
     
+    // Una política inválida no impide cargar la cámara: usa la global
+    for camera in &mut config_file.cameras {
+        if let Some(Err(e)) = camera.reconnect.as_ref().map(|p| p.validate()) {
+            log::warn!("Ignoring invalid reconnect policy for camera {}: {}", camera.id, e);
+            camera.reconnect = None;
+        }
+    }
+    
     log::info!("Loaded {} cameras from config", config_file.cameras.len());
     Ok(config_file.cameras)
 }
 
-/// Guarda configuración de cámaras a archivo
+/// Comprueba que un contenido de `cameras.json` se puede cargar, sin escribir nada
+fn parse_cameras_config(content: &[u8]) -> Result<CamerasConfigFile> {
+    let mut value: serde_json::Value = serde_json::from_slice(content)
+        .context("Failed to parse cameras config")?;
+    config_manager::migrate(&mut value, CAMERAS_MIGRATIONS)?;
+    serde_json::from_value(value).context("Failed to parse cameras config")
+}
+
+/// Guarda configuración de cámaras a archivo, ordenadas por id para que el
+/// mismo contenido dé siempre el mismo archivo (y no rote backups de más)
 pub fn save_cameras_config(
-    cameras: &HashMap<String, CameraRuntime>,
+    cameras: &HashMap<String, CameraHandle>,
     config_path: &Path,
 ) -> Result<()> {
-    let configs: Vec<CameraConfig> = cameras.values()
-        .map(|runtime| runtime.config.clone())
+    let mut configs: Vec<CameraConfig> = cameras.values()
+        .map(|camera| camera.snapshot().config)
         .collect();
+    configs.sort_by(|a, b| a.id.cmp(&b.id));
     
-    let config_file = CamerasConfigFile { cameras: configs };
+    config_manager::ensure_not_newer(config_path, CAMERAS_MIGRATIONS)
+        .context("Refusing to overwrite cameras config")?;
+    
+    let config_file = CamerasConfigFile { cameras: configs, ..Default::default() };
     
     let content = serde_json::to_string_pretty(&config_file)
         .context("Failed to serialize cameras config")?;
     
-    std::fs::write(config_path, content)
+    config_manager::write_with_backups(config_path, content.as_bytes(), config_manager::CAMERAS_BACKUPS)
         .context("Failed to write cameras config")?;
     
     log::info!("Saved {} cameras to config", config_file.cameras.len());
     Ok(())
 }
 
-/// Inicializa cámaras desde configuración guardada
+/// Inicializa cámaras desde configuración guardada. Devuelve el aviso de
+/// `load_cameras_config` si hubo que restaurar un backup.
 pub async fn init_cameras_from_config(
-    cameras: &mut HashMap<String, CameraRuntime>,
+    cameras: &Mutex<HashMap<String, CameraHandle>>,
     config_path: &Path,
-    ffmpeg_path: &Path,
+    launch: &LaunchContext,
+    default_policy: &ReconnectPolicy,
+    app_handle: Option<tauri::AppHandle>,
     auto_start: bool,
-) -> Result<usize> {
-    let configs = load_cameras_config(config_path)?;
+) -> Result<Option<String>> {
+    let LoadedCameras { cameras: configs, warning } = load_cameras_config(config_path)?;
     let total = configs.len();
     let mut started = 0;
     
     for config in configs {
         let id = config.id.clone();
         let enabled = config.enabled;
-        add_camera(cameras, config)?;
+        add_camera(cameras, config, default_policy, app_handle.clone()).await?;
         
         if auto_start && enabled {
             log::info!("Auto-starting camera: {}", id);
-            match start_camera(cameras, &id, ffmpeg_path).await {
-                Ok(_) => {
+            match start_camera(cameras, &id, launch.clone()).await {
+                Ok(ProcessStatus::Running) => {
                     started += 1;
                     log::info!("Camera {} started successfully", id);
                 }
+                Ok(status) => {
+                    log::warn!("Camera {} did not start yet ({})", id, status.as_str());
+                }
                 Err(e) => {
                     log::error!("Failed to auto-start camera {}: {}", id, e);
                 }
@@ -293,5 +426,126 @@ pub async fn init_cameras_from_config(
     }
     
     log::info!("Initialized {}/{} cameras (started: {})", total, total, started);
-    Ok(total)
+    Ok(warning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn camera(id: &str) -> CameraConfig {
+        CameraConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            rtsp_url: format!("rtsp://192.0.2.10:554/{}", id),
+            enabled: true,
+            encoding: EncodingMode::Copy,
+            quality: QualityPreset::Medium,
+            audio_mode: AudioMode::Copy,
+            reconnect: None,
+        }
+    }
+
+    /// `cameras.json` con una cámara en un directorio propio del test
+    fn temp_cameras_config(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stream-agent-cameras-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cameras.json");
+        let file = CamerasConfigFile { cameras: vec![camera("cam1")], ..Default::default() };
+        std::fs::write(&path, serde_json::to_string_pretty(&file).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_damaged_cameras_config_restored_from_backup() {
+        let config = temp_cameras_config("restore");
+        let good = std::fs::read_to_string(&config).unwrap();
+        std::fs::write(config_manager::backup_path(&config, 2), &good).unwrap();
+        std::fs::write(config_manager::backup_path(&config, 1), "{\"cameras\": [").unwrap();
+        std::fs::write(&config, &good[..good.len() / 2]).unwrap();
+
+        // Se saltea el backup dañado y se usa el siguiente
+        let loaded = load_cameras_config(&config).unwrap();
+        assert_eq!(loaded.cameras.len(), 1);
+        let warning = loaded.warning.expect("restore warning");
+        assert!(warning.contains("cameras.json.bak.2"), "{}", warning);
+        assert_eq!(
+            std::fs::read_to_string(config.with_extension("json.corrupt")).unwrap(),
+            &good[..good.len() / 2]
+        );
+        assert!(load_cameras_config(&config).unwrap().warning.is_none());
+
+        let _ = std::fs::remove_dir_all(config.parent().unwrap());
+    }
+
+    #[test]
+    fn test_newer_cameras_config_not_replaced_by_backup() {
+        let config = temp_cameras_config("newer");
+        let backup = config_manager::backup_path(&config, 1);
+        let newer = "{\"schemaVersion\": 99, \"cameras\": []}";
+        std::fs::copy(&config, &backup).unwrap();
+        std::fs::write(&config, newer).unwrap();
+        let backup_content = std::fs::read(&backup).unwrap();
+
+        let error = load_cameras_config(&config).unwrap_err();
+        assert!(error.downcast_ref::<NewerSchemaVersion>().is_some(), "{:#}", error);
+        assert_eq!(std::fs::read_to_string(&config).unwrap(), newer);
+        assert_eq!(std::fs::read(&backup).unwrap(), backup_content);
+        assert!(!config.with_extension("json.corrupt").exists());
+
+        // Tampoco se pisa al guardar cambios
+        assert!(save_cameras_config(&HashMap::new(), &config).is_err());
+        assert_eq!(std::fs::read_to_string(&config).unwrap(), newer);
+
+        let _ = std::fs::remove_dir_all(config.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_save_cameras_config_is_stable() {
+        let config = temp_cameras_config("stable");
+        let policy = ReconnectPolicy::default();
+        let ids = ["cam3", "cam1", "cam2", "cam10"];
+        let mut cameras = HashMap::new();
+        for id in ids {
+            insert_cameras(&mut cameras, &[camera(id)], &policy, None).unwrap();
+        }
+
+        save_cameras_config(&cameras, &config).unwrap();
+        let saved = std::fs::read(&config).unwrap();
+        let loaded = load_cameras_config(&config).unwrap().cameras;
+        let order: Vec<&str> = loaded.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(order, vec!["cam1", "cam10", "cam2", "cam3"]);
+
+        // Volver a guardar lo mismo no cambia el archivo ni rota un backup
+        let backup = config_manager::backup_path(&config, 1);
+        let backup_before = std::fs::read(&backup).ok();
+        save_cameras_config(&cameras, &config).unwrap();
+        assert_eq!(std::fs::read(&config).unwrap(), saved);
+        assert_eq!(std::fs::read(&backup).ok(), backup_before);
+
+        let _ = std::fs::remove_dir_all(config.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_insert_cameras_all_or_nothing() {
+        let policy = ReconnectPolicy::default();
+        let mut cameras = HashMap::new();
+        insert_cameras(&mut cameras, &[camera("cam1")], &policy, None).unwrap();
+
+        // Un id que ya existe frena todo el lote
+        assert!(insert_cameras(&mut cameras, &[camera("cam2"), camera("cam1")], &policy, None).is_err());
+        // También un id repetido dentro del lote
+        assert!(insert_cameras(&mut cameras, &[camera("cam2"), camera("cam2")], &policy, None).is_err());
+        // O una política inválida
+        let mut invalid = camera("cam3");
+        invalid.reconnect = Some(ReconnectPolicy { retry_delay_ms: 0, ..ReconnectPolicy::default() });
+        assert!(insert_cameras(&mut cameras, &[camera("cam2"), invalid], &policy, None).is_err());
+        assert_eq!(cameras.len(), 1);
+
+        insert_cameras(&mut cameras, &[camera("cam2"), camera("cam3")], &policy, None).unwrap();
+        let mut ids: Vec<&String> = cameras.keys().collect();
+        ids.sort();
+        assert_eq!(ids, vec!["cam1", "cam2", "cam3"]);
+    }
 }
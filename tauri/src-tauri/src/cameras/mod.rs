@@ -1,3 +1,5 @@
+pub mod camera_actor;
 pub mod camera_manager;
 
+pub use camera_actor::*;
 pub use camera_manager::*;
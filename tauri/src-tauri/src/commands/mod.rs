@@ -2,6 +2,7 @@ use crate::app_state::*;
 use crate::cameras::*;
 use crate::config_manager::ConfigManager;
 use crate::supervisor::*;
+use std::collections::HashMap;
 use tauri::{Emitter, Manager, State};
 
 /// Inicia el agente (MediaMTX, Cloudflared, cámaras)
@@ -10,29 +11,56 @@ pub async fn start_agent(
     state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
+    launch_agent(state.inner(), &app_handle).await?;
+    Ok("Agent started successfully".to_string())
+}
+
+/// Resuelve binarios y configuración e inicia el agente. La usan el comando
+/// `start_agent` y el inicio automático de `autoStartAgent`.
+pub async fn launch_agent(
+    state: &AppState,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), String> {
     log::info!("=== Starting Agent ===");
     
-    let mut is_running = state.is_running.lock().await;
-    if *is_running {
-        return Err("Agent is already running".to_string());
+    let current = state.lifecycle.lock().await.state;
+    if current != AgentState::Stopped {
+        return Err(format!("Agent is already {}", current.as_str()));
+    }
+    
+    // Los huérfanos de la ejecución anterior ocupan los puertos de MediaMTX
+    if let Some(cleanup) = state.orphan_cleanup.lock().await.take() {
+        log::info!("Waiting for orphaned processes to be cleaned up...");
+        if let Err(e) = cleanup.await {
+            log::error!("Orphan cleanup failed: {}", e);
+        }
     }
     
     // Crear ConfigManager
-    let config_mgr = ConfigManager::new(&app_handle)
+    let config_mgr = ConfigManager::new(app_handle)
         .map_err(|e| format!("Failed to initialize config manager: {}", e))?;
     
     // Asegurar que los configs existen
     config_mgr.initialize_configs()
         .map_err(|e| format!("Failed to initialize configs: {}", e))?;
     
-    // Obtener rutas de binarios (con fallback a desarrollo)
-    let mediamtx_bin = config_mgr.get_binary_path("mediamtx.exe")
+    // Obtener rutas de binarios (resources, config.json o PATH)
+    let binaries = config_mgr.binary_resolver(state.config.lock().await.binary_paths.clone());
+    let mediamtx = binaries.resolve(MEDIAMTX)
         .map_err(|e| format!("MediaMTX binary not found: {}", e))?;
-    let ffmpeg_bin = config_mgr.get_binary_path("ffmpeg.exe")
+    let ffmpeg = binaries.resolve(FFMPEG)
         .map_err(|e| format!("FFmpeg binary not found: {}", e))?;
     
-    log::info!("MediaMTX binary: {:?}", mediamtx_bin);
-    log::info!("FFmpeg binary: {:?}", ffmpeg_bin);
+    let cloudflared_bin = binaries.resolve(CLOUDFLARED).ok();
+    
+    log::info!("MediaMTX binary: {:?} ({:?})", mediamtx.path, mediamtx.source);
+    log::info!("FFmpeg binary: {:?} ({:?})", ffmpeg.path, ffmpeg.source);
+    
+    // El estado informa estos binarios mientras el agente no esté detenido
+    *state.binaries.lock().await = [Some(mediamtx.clone()), Some(ffmpeg.clone()), cloudflared_bin.clone()]
+        .into_iter()
+        .flatten()
+        .collect();
     
     let mediamtx_config = config_mgr.get_config_path("mediamtx.yml");
     let cameras_config = config_mgr.get_config_path("cameras.json");
@@ -40,105 +68,101 @@ pub async fn start_agent(
     log::info!("MediaMTX config: {:?}", mediamtx_config);
     log::info!("Cameras config: {:?}", cameras_config);
     
-    // Iniciar MediaMTX
-    log::info!("Starting MediaMTX...");
-    let mediamtx_handle = start_mediamtx(&mediamtx_bin, &mediamtx_config)
-        .map_err(|e| format!("Failed to start MediaMTX: {}", e))?;
-    
-    {
-        let mut mediamtx_proc = state.mediamtx_process.lock().await;
-        *mediamtx_proc = Some(mediamtx_handle);
-    }
-    log::info!("MediaMTX started successfully");
-    
-    // Esperar un momento para que MediaMTX inicie
-    tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-    
-    // Intentar iniciar Cloudflared
-    match start_cloudflared_auto(&app_handle, &config_mgr).await {
-        Ok(Some(handle)) => {
-            let mut cloudflared_proc = state.cloudflared_process.lock().await;
-            *cloudflared_proc = Some(handle);
-            log::info!("Cloudflared started successfully");
-        }
-        Ok(None) => {
-            log::info!("Cloudflared not started (no configuration)");
-        }
+    let agent_config = state.config.lock().await.clone();
+    let cloudflared = match resolve_cloudflared_launch(&config_mgr, cloudflared_bin.as_ref(), &agent_config) {
+        Ok(launch) => launch,
         Err(e) => {
-            log::warn!("Failed to start Cloudflared (non-critical): {}", e);
+            log::warn!("Failed to prepare Cloudflared (non-critical): {}", e);
+            None
         }
-    }
-    
-    // Cargar y auto-iniciar cámaras habilitadas (limpiar estado previo primero)
-    {
-        let mut cameras = state.cameras.lock().await;
-        // Limpiar cámaras existentes en memoria antes de cargar desde config
-        cameras.clear();
-        
-        match init_cameras_from_config(&mut cameras, &cameras_config, &ffmpeg_bin, true).await {
-            Ok(count) => {
-                log::info!("Initialized {} cameras", count);
-            }
-            Err(e) => {
-                log::error!("Failed to init cameras: {}", e);
-            }
-        }
-    }
-    
-    *is_running = true;
-    
-    log::info!("=== Agent Started Successfully ===");
-    app_handle.emit("agent-status-changed", AgentStatusPayload { running: true })
-        .map_err(|e| e.to_string())?;
+    };
     
-    Ok("Agent started successfully".to_string())
+    // Consultar qué soporta el FFmpeg instalado antes de armar sus argumentos
+    let capabilities = probe_tools(&binaries).await;
+    *state.capabilities.lock().await = Some(capabilities.clone());
+    
+    let launch = crate::agent::AgentLaunch {
+        mediamtx_bin: mediamtx.path,
+        mediamtx_config,
+        ffmpeg_bin: ffmpeg.path,
+        cameras_config,
+        cloudflared,
+        ffmpeg_capabilities: capabilities.ffmpeg,
+        watchdog_interval: WATCHDOG_INTERVAL,
+        auto_start_cameras: agent_config.auto_start_cameras,
+    };
+    crate::agent::start_agent(state, launch, Some(app_handle)).await
+        .map_err(|e| format!("{:#}", e))
 }
 
-/// Intenta iniciar cloudflared automáticamente
-async fn start_cloudflared_auto(
-    app_handle: &tauri::AppHandle,
+/// Determina cómo lanzar cloudflared según `tunnelMode`: con un túnel
+/// existente configurado en el sistema o en modo Quick Tunnel.
+/// Devuelve `None` si el túnel está deshabilitado o no hay binario.
+fn resolve_cloudflared_launch(
     config_mgr: &ConfigManager,
-) -> Result<Option<ProcessHandle>, String> {
+    cloudflared_bin: Option<&ResolvedBinary>,
+    config: &AgentConfig,
+) -> Result<Option<CloudflaredLaunch>, String> {
+    if !config.tunnel_enabled {
+        log::info!("Tunnel disabled in config, skipping Cloudflared");
+        return Ok(None);
+    }
+    
     // Buscar binario de cloudflared
-    let cloudflared_bin = match config_mgr.get_binary_path("cloudflared.exe") {
-        Ok(path) => path,
-        Err(_) => {
+    let cloudflared_bin = match cloudflared_bin {
+        Some(binary) => {
+            log::info!("Cloudflared binary: {:?} ({:?})", binary.path, binary.source);
+            binary.path.clone()
+        }
+        None => {
             log::info!("Cloudflared binary not found, skipping tunnel");
             return Ok(None);
         }
     };
     
     // Detectar si hay túneles configurados en el sistema
-    if let Some(tunnel_info) = crate::config_manager::detect_cloudflared_tunnel() {
-        if tunnel_info.is_authenticated && !tunnel_info.tunnels.is_empty() {
-            // Usar el primer túnel configurado
-            let tunnel = &tunnel_info.tunnels[0];
+    if config.tunnel_mode != TunnelMode::Quick {
+        let tunnel_info = crate::config_manager::detect_cloudflared_tunnel()
+            .filter(|info| info.is_authenticated);
+        // Preferir el túnel de `tunnelId`; si no, el primero configurado
+        let tunnel = tunnel_info.as_ref().and_then(|info| match &config.tunnel_id {
+            Some(id) => info.tunnels.iter().find(|t| &t.tunnel_id == id),
+            None => info.tunnels.first(),
+        });
+        
+        if let Some(tunnel) = tunnel {
             log::info!("Using existing tunnel: {}", tunnel.tunnel_id);
             
-            // Generar config si no existe
+            // Regenerar siempre: el hostname o el túnel pueden haber cambiado
             let cloudflared_config = config_mgr.get_config_path("cloudflared-config.yml");
-            if !cloudflared_config.exists() {
-                crate::config_manager::generate_cloudflared_config(
-                    &tunnel.tunnel_id,
-                    &tunnel.credentials_file,
-                    None, // Sin hostname, usar el por defecto del tunnel
-                    8888, // Puerto HLS de MediaMTX
-                    &cloudflared_config,
-                ).map_err(|e| e.to_string())?;
-            }
+            crate::config_manager::generate_cloudflared_config(
+                &tunnel.tunnel_id,
+                &tunnel.credentials_file,
+                config.tunnel_hostname.as_deref(),
+                8888, // Puerto HLS de MediaMTX
+                &cloudflared_config,
+            ).map_err(|e| e.to_string())?;
             
-            let handle = start_cloudflared(&cloudflared_bin, &cloudflared_config)
-                .map_err(|e| format!("Failed to start cloudflared: {}", e))?;
-            return Ok(Some(handle));
+            return Ok(Some(CloudflaredLaunch {
+                bin_path: cloudflared_bin,
+                mode: CloudflaredMode::Named { config_path: cloudflared_config },
+            }));
+        }
+        
+        if config.tunnel_mode == TunnelMode::Named {
+            return Err(match &config.tunnel_id {
+                Some(id) => format!("Named tunnel {} not found in ~/.cloudflared", id),
+                None => "No authenticated named tunnel found in ~/.cloudflared".to_string(),
+            });
         }
     }
     
-    // Si no hay túnel configurado, intentar modo Quick Tunnel (sin config)
-    log::info!("No existing tunnel found, trying Quick Tunnel mode...");
-    let handle = start_cloudflared_quick_tunnel(&cloudflared_bin, 8888)
-        .map_err(|e| format!("Failed to start quick tunnel: {}", e))?;
-    
-    Ok(Some(handle))
+    // Sin túnel con nombre, modo Quick Tunnel (sin config)
+    log::info!("Using Quick Tunnel mode");
+    Ok(Some(CloudflaredLaunch {
+        bin_path: cloudflared_bin,
+        mode: CloudflaredMode::Quick { local_port: 8888 },
+    }))
 }
 
 /// Detiene el agente
@@ -147,99 +171,100 @@ pub async fn stop_agent(
     state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    log::info!("Stopping agent...");
-    
-    let mut is_running = state.is_running.lock().await;
-    if !*is_running {
-        return Err("Agent is not running".to_string());
-    }
-    
-    // Detener todas las cámaras
-    {
-        let mut cameras = state.cameras.lock().await;
-        if let Err(e) = stop_all_cameras(&mut cameras) {
-            log::error!("Error stopping cameras: {}", e);
-        }
-    }
-    
-    // Detener Cloudflared
-    {
-        let mut cloudflared_proc = state.cloudflared_process.lock().await;
-        if let Some(handle) = cloudflared_proc.as_mut() {
-            if let Err(e) = stop_process(handle) {
-                log::error!("Error stopping Cloudflared: {}", e);
-            }
-        }
-        *cloudflared_proc = None;
-    }
-    
-    // Detener MediaMTX
-    {
-        let mut mediamtx_proc = state.mediamtx_process.lock().await;
-        if let Some(handle) = mediamtx_proc.as_mut() {
-            if let Err(e) = stop_process(handle) {
-                log::error!("Error stopping MediaMTX: {}", e);
-            }
-        }
-        *mediamtx_proc = None;
-    }
-    
-    *is_running = false;
-    
-    log::info!("Agent stopped successfully");
-    app_handle.emit("agent-status-changed", AgentStatusPayload { running: false })
+    crate::agent::shutdown_agent(state.inner(), Some(&app_handle), "Stop requested").await
         .map_err(|e| e.to_string())?;
-    
     Ok("Agent stopped successfully".to_string())
 }
 
 /// Obtiene el estado del agente
 #[tauri::command]
-pub async fn get_agent_status(state: State<'_, AppState>) -> Result<AgentStatus, String> {
-    let is_running = *state.is_running.lock().await;
-    
-    let mediamtx_running = {
-        let mediamtx_proc = state.mediamtx_process.lock().await;
-        mediamtx_proc.is_some()
+pub async fn get_agent_status(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<AgentStatus, String> {
+    let lifecycle = state.lifecycle.lock().await.clone();
+    
+    // Solo lectura: se informa lo último que registró el watchdog, que es
+    // quien detecta (y reinicia) los procesos caídos
+    let (mediamtx_running, mediamtx_status, mediamtx_restarts, uptime_secs, mediamtx_resources) = {
+        let mediamtx = state.mediamtx.lock().await;
+        let alive = mediamtx.process.is_some() && mediamtx.status == ProcessStatus::Running;
+        (
+            alive,
+            mediamtx.status.clone(),
+            mediamtx.stats.restarts,
+            // Uptime del agente: desde que MediaMTX inició
+            mediamtx.uptime_secs(),
+            mediamtx.resources.current().filter(|_| alive).cloned(),
+        )
     };
     
-    let cloudflared_running = {
-        let cloudflared_proc = state.cloudflared_process.lock().await;
-        cloudflared_proc.is_some()
+    let (cloudflared_running, cloudflared_status, cloudflared_resources) = {
+        let cloudflared = state.cloudflared.lock().await;
+        let alive = cloudflared.process.is_some() && cloudflared.status == ProcessStatus::Running;
+        (
+            alive,
+            cloudflared.status.clone(),
+            cloudflared.resources.current().filter(|_| alive).cloned(),
+        )
     };
     
+    let tunnel = state.tunnel.lock()
+        .map(|t| t.clone())
+        .unwrap_or_default();
+    
     let (cameras_running, cameras_total) = {
         let cameras = state.cameras.lock().await;
         let running = cameras.values()
-            .filter(|c| c.status == ProcessStatus::Running)
+            .filter(|c| c.snapshot().status == ProcessStatus::Running)
             .count() as u32;
         let total = cameras.len() as u32;
         (running, total)
     };
     
-    let tunnel_url = {
-        let config = state.config.lock().await;
-        config.tunnel_hostname.clone()
+    // URL del Quick Tunnel si la hay, si no el hostname configurado
+    let tunnel_url = match tunnel.url {
+        Some(url) => Some(url),
+        None => state.config.lock().await.tunnel_hostname.clone(),
     };
     
-    // Calcular uptime desde que MediaMTX inició
-    let uptime_secs = {
-        let mediamtx_proc = state.mediamtx_process.lock().await;
-        if let Some(handle) = mediamtx_proc.as_ref() {
-            handle.uptime().as_secs()
-        } else {
-            0
+    // Con el agente arriba se informan los binarios con los que se lanzó;
+    // detenido se vuelven a buscar por si el usuario los instaló o movió
+    let (binaries, missing_binaries) = if lifecycle.state == AgentState::Stopped {
+        let binary_paths = state.config.lock().await.binary_paths.clone();
+        match BinaryResolver::from_app(&app_handle, binary_paths) {
+            Ok(resolver) => resolver.resolve_all(),
+            Err(e) => {
+                log::warn!("Failed to resolve binaries for status: {:#}", e);
+                (Vec::new(), missing_binaries(&[]))
+            }
         }
+    } else {
+        let binaries = state.binaries.lock().await.clone();
+        let missing = missing_binaries(&binaries);
+        (binaries, missing)
     };
     
     Ok(AgentStatus {
-        running: is_running,
+        running: lifecycle.state.is_active(),
+        state: lifecycle.state,
+        state_reason: lifecycle.reason,
+        state_since: lifecycle.since,
         mediamtx_running,
+        mediamtx_status,
+        mediamtx_restarts,
         cloudflared_running,
+        cloudflared_status,
+        tunnel_connected: cloudflared_running && tunnel.connected,
         cameras_running,
         cameras_total,
         tunnel_url,
         uptime_secs,
+        mediamtx_resources,
+        cloudflared_resources,
+        binaries,
+        missing_binaries,
+        config_warnings: state.config_warnings.lock().await.clone(),
     })
 }
 
@@ -250,6 +275,37 @@ pub async fn list_cameras(state: State<'_, AppState>) -> Result<Vec<CameraInfo>,
     Ok(crate::cameras::list_cameras(&cameras))
 }
 
+/// Obtiene métricas en vivo de una cámara y su historial reciente
+#[tauri::command]
+pub async fn get_camera_metrics(
+    state: State<'_, AppState>,
+    id: String,
+    window_secs: Option<u64>,
+) -> Result<CameraMetrics, String> {
+    let cameras = state.cameras.lock().await;
+    crate::cameras::get_camera_metrics(&cameras, &id, window_secs)
+        .map_err(|e| e.to_string())
+}
+
+/// Obtiene el consumo de MediaMTX o cloudflared y su historial reciente
+#[tauri::command]
+pub async fn get_service_resources(
+    state: State<'_, AppState>,
+    service: String,
+    window_secs: Option<u64>,
+) -> Result<ServiceResources, String> {
+    let runtime = match service.as_str() {
+        "mediamtx" => state.mediamtx.lock().await,
+        "cloudflared" => state.cloudflared.lock().await,
+        _ => return Err(format!("Unknown service '{}'", service)),
+    };
+    Ok(ServiceResources {
+        current: runtime.resources.current().cloned(),
+        history: runtime.resources.history(window_secs),
+        service,
+    })
+}
+
 /// Agrega una nueva cámara
 #[tauri::command]
 pub async fn add_camera(
@@ -257,14 +313,14 @@ pub async fn add_camera(
     camera: CameraConfig,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let mut cameras = state.cameras.lock().await;
-    crate::cameras::add_camera(&mut cameras, camera)
+    let reconnect_policy = state.config.lock().await.reconnect_policy.clone();
+    crate::cameras::add_camera(&state.cameras, camera, &reconnect_policy, Some(app_handle.clone())).await
         .map_err(|e| e.to_string())?;
     
     // Guardar configuración
     let config_dir = get_config_dir(&app_handle).map_err(|e| e.to_string())?;
     let cameras_config = config_dir.join("cameras.json");
-    save_cameras_config(&cameras, &cameras_config).map_err(|e| e.to_string())?;
+    save_cameras_config(&*state.cameras.lock().await, &cameras_config).map_err(|e| e.to_string())?;
     
     app_handle.emit("cameras-updated", ()).map_err(|e| e.to_string())?;
     
@@ -279,14 +335,13 @@ pub async fn update_camera(
     updates: CameraConfig,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let mut cameras = state.cameras.lock().await;
-    crate::cameras::update_camera(&mut cameras, &id, updates)
+    crate::cameras::update_camera(&state.cameras, &id, updates).await
         .map_err(|e| e.to_string())?;
     
     // Guardar configuración
     let config_dir = get_config_dir(&app_handle).map_err(|e| e.to_string())?;
     let cameras_config = config_dir.join("cameras.json");
-    save_cameras_config(&cameras, &cameras_config).map_err(|e| e.to_string())?;
+    save_cameras_config(&*state.cameras.lock().await, &cameras_config).map_err(|e| e.to_string())?;
     
     app_handle.emit("cameras-updated", ()).map_err(|e| e.to_string())?;
     
@@ -300,14 +355,14 @@ pub async fn remove_camera(
     id: String,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let mut cameras = state.cameras.lock().await;
-    crate::cameras::remove_camera(&mut cameras, &id)
+    let shutdown = state.config.lock().await.shutdown_policy.clone();
+    crate::cameras::remove_camera(&state.cameras, &id, &shutdown).await
         .map_err(|e| e.to_string())?;
     
     // Guardar configuración
     let config_dir = get_config_dir(&app_handle).map_err(|e| e.to_string())?;
     let cameras_config = config_dir.join("cameras.json");
-    save_cameras_config(&cameras, &cameras_config).map_err(|e| e.to_string())?;
+    save_cameras_config(&*state.cameras.lock().await, &cameras_config).map_err(|e| e.to_string())?;
     
     app_handle.emit("cameras-updated", ()).map_err(|e| e.to_string())?;
     
@@ -321,17 +376,14 @@ pub async fn start_camera(
     id: String,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let ffmpeg_bin = get_binary_path(&app_handle, "ffmpeg.exe")
-        .map_err(|e| format!("FFmpeg binary not found: {}", e))?;
-    
-    let mut cameras = state.cameras.lock().await;
-    crate::cameras::start_camera(&mut cameras, &id, &ffmpeg_bin).await
+    let launch = camera_launch(&state, &app_handle).await?;
+    let status = crate::cameras::start_camera(&state.cameras, &id, launch).await
         .map_err(|e| e.to_string())?;
     
-    app_handle.emit("camera-status-changed", CameraStatusPayload { id: id.clone(), status: "running".to_string() })
-        .map_err(|e| e.to_string())?;
-    
-    Ok(format!("Camera {} started successfully", id))
+    match status {
+        ProcessStatus::Running => Ok(format!("Camera {} started successfully", id)),
+        _ => Ok(format!("Camera {} failed to start, retrying in background", id)),
+    }
 }
 
 /// Detiene una cámara específica
@@ -339,16 +391,20 @@ pub async fn start_camera(
 pub async fn stop_camera(
     state: State<'_, AppState>,
     id: String,
-    app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let mut cameras = state.cameras.lock().await;
-    crate::cameras::stop_camera(&mut cameras, &id)
-        .map_err(|e| e.to_string())?;
-    
-    app_handle.emit("camera-status-changed", CameraStatusPayload { id: id.clone(), status: "stopped".to_string() })
+    let shutdown = state.config.lock().await.shutdown_policy.clone();
+    let outcome = crate::cameras::stop_camera(&state.cameras, &id, &shutdown).await
         .map_err(|e| e.to_string())?;
     
-    Ok(format!("Camera {} stopped successfully", id))
+    match outcome {
+        Some(outcome) => Ok(format!(
+            "Camera {} stopped successfully ({:?}, {})",
+            id,
+            outcome.stage,
+            outcome.exit_status.map_or("unknown exit status".to_string(), |s| s.to_string())
+        )),
+        None => Ok(format!("Camera {} stopped successfully", id)),
+    }
 }
 
 /// Reconecta una cámara (stop + start)
@@ -358,17 +414,293 @@ pub async fn reconnect_camera(
     id: String,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    let ffmpeg_bin = get_binary_path(&app_handle, "ffmpeg.exe")
-        .map_err(|e| format!("FFmpeg binary not found: {}", e))?;
+    let launch = camera_launch(&state, &app_handle).await?;
+    let shutdown = state.config.lock().await.shutdown_policy.clone();
+    let status = crate::cameras::reconnect_camera(&state.cameras, &id, launch, &shutdown).await
+        .map_err(|e| e.to_string())?;
     
-    let mut cameras = state.cameras.lock().await;
-    crate::cameras::reconnect_camera(&mut cameras, &id, &ffmpeg_bin).await
+    match status {
+        ProcessStatus::Running => Ok(format!("Camera {} reconnected successfully", id)),
+        _ => Ok(format!("Camera {} failed to restart, retrying in background", id)),
+    }
+}
+
+/// Saca una cámara de cuarentena y la relanza sin esperar el cooldown
+#[tauri::command]
+pub async fn clear_camera_quarantine(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<String, String> {
+    let status = crate::cameras::clear_camera_quarantine(&state.cameras, &id).await
         .map_err(|e| e.to_string())?;
     
-    app_handle.emit("camera-status-changed", CameraStatusPayload { id: id.clone(), status: "running".to_string() })
+    match status {
+        ProcessStatus::Running => Ok(format!("Camera {} released from quarantine and restarted", id)),
+        _ => Ok(format!("Camera {} released from quarantine, restart failed ({})", id, status.as_str())),
+    }
+}
+
+/// Obtiene la política de reconexión de una cámara (la que aplica, propia o
+/// global) o, sin `camera_id`, la global
+#[tauri::command]
+pub async fn get_reconnect_policy(
+    state: State<'_, AppState>,
+    camera_id: Option<String>,
+) -> Result<ReconnectPolicy, String> {
+    match camera_id {
+        Some(id) => {
+            let camera = get_camera(&state.cameras, &id).await.map_err(|e| e.to_string())?;
+            let default = state.config.lock().await.reconnect_policy.clone();
+            Ok(camera.snapshot().config.effective_reconnect_policy(&default))
+        }
+        None => Ok(state.config.lock().await.reconnect_policy.clone()),
+    }
+}
+
+/// Cambia la política de reconexión de una cámara (`policy: null` la vuelve a
+/// la global) o, sin `camera_id`, la global. Se guarda en `cameras.json` o
+/// `config.json` y rige desde el próximo fallo.
+#[tauri::command]
+pub async fn set_reconnect_policy(
+    state: State<'_, AppState>,
+    camera_id: Option<String>,
+    policy: Option<ReconnectPolicy>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    if let Some(policy) = &policy {
+        policy.validate().map_err(|e| format!("Invalid reconnect policy: {}", e))?;
+    }
+    let config_dir = get_config_dir(&app_handle).map_err(|e| e.to_string())?;
+    
+    if let Some(id) = camera_id {
+        set_camera_reconnect_policy(&state.cameras, &id, policy).await
+            .map_err(|e| e.to_string())?;
+        
+        let cameras_config = config_dir.join("cameras.json");
+        save_cameras_config(&*state.cameras.lock().await, &cameras_config).map_err(|e| e.to_string())?;
+        app_handle.emit("cameras-updated", ()).map_err(|e| e.to_string())?;
+        
+        return Ok(format!("Reconnect policy of camera {} updated", id));
+    }
+    
+    let policy = policy.ok_or_else(|| "A policy is required to update the global default".to_string())?;
+    let mut config = state.config.lock().await;
+    let mut updated = config.clone();
+    updated.reconnect_policy = policy.clone();
+    crate::config_manager::save_agent_config(&config_dir.join("config.json"), &updated)
         .map_err(|e| e.to_string())?;
+    *config = updated;
+    drop(config);
+    
+    apply_reconnect_policy(state.inner(), &policy).await;
+    
+    Ok("Global reconnect policy updated".to_string())
+}
+
+/// Aplica la política global a los servicios y a las cámaras sin política
+/// propia
+async fn apply_reconnect_policy(state: &AppState, policy: &ReconnectPolicy) {
+    state.mediamtx.lock().await.reconnect_policy = policy.clone();
+    state.cloudflared.lock().await.reconnect_policy = policy.clone();
+    set_default_reconnect_policy(&state.cameras, policy).await;
+}
+
+/// Configuración general del agente (`config.json`)
+#[tauri::command]
+pub async fn get_agent_config(state: State<'_, AppState>) -> Result<AgentConfig, String> {
+    Ok(state.config.lock().await.clone())
+}
+
+/// Valida y guarda la configuración general. Si algún campo no es válido no
+/// se guarda nada y se devuelven los errores por campo. Los cambios de
+/// túnel y servidor rigen desde el próximo inicio del agente; la política de
+/// reconexión se aplica de inmediato.
+#[tauri::command]
+pub async fn update_agent_config(
+    state: State<'_, AppState>,
+    mut config: AgentConfig,
+    app_handle: tauri::AppHandle,
+) -> Result<AgentConfig, ConfigUpdateError> {
+    config.schema_version = CONFIG_SCHEMA_VERSION;
+    let fields = config.validate();
+    if !fields.is_empty() {
+        return Err(ConfigUpdateError { message: "Invalid agent config".to_string(), fields });
+    }
+    
+    let config_path = get_config_dir(&app_handle)
+        .map_err(|e| ConfigUpdateError::from_message(e.to_string()))?
+        .join("config.json");
+    crate::config_manager::save_agent_config(&config_path, &config)
+        .map_err(|e| ConfigUpdateError::from_message(format!("{:#}", e)))?;
+    
+    *state.config.lock().await = config.clone();
+    apply_reconnect_policy(state.inner(), &config.reconnect_policy).await;
+    
+    app_handle.emit("agent-config-updated", &config)
+        .map_err(|e| ConfigUpdateError::from_message(e.to_string()))?;
+    log::info!("Agent config updated");
+    Ok(config)
+}
+
+/// Importa cámaras y configuración de stream-agent-electron o del servidor
+/// Node desde sus `cameras.json` / `config.json`. Con `dry_run` solo devuelve
+/// lo que se importaría. Las cámaras cuyo id ya existe no se tocan.
+#[tauri::command]
+pub async fn import_legacy_config(
+    state: State<'_, AppState>,
+    cameras_path: Option<String>,
+    config_path: Option<String>,
+    dry_run: bool,
+    app_handle: tauri::AppHandle,
+) -> Result<LegacyImport, String> {
+    if cameras_path.is_none() && config_path.is_none() {
+        return Err("Select a cameras.json or config.json to import".to_string());
+    }
+    let read = |path: &Option<String>| {
+        path.as_ref()
+            .map(|path| std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e)))
+            .transpose()
+    };
+    let cameras_json = read(&cameras_path)?;
+    let config_json = read(&config_path)?;
+    
+    let base = state.config.lock().await.clone();
+    let mut import = crate::config_manager::import_legacy(cameras_json.as_deref(), config_json.as_deref(), &base)
+        .map_err(|e| format!("{:#}", e))?;
+    let config_dir = get_config_dir(&app_handle).map_err(|e| e.to_string())?;
+    let cameras_path = config_dir.join("cameras.json");
+    
+    // Revisar ids, agregar y guardar cameras.json con un solo lock para que
+    // nada se cuele entre medio
+    let mut cameras = state.cameras.lock().await;
+    let issues = &mut import.issues;
+    import.cameras.retain(|camera| {
+        let exists = cameras.contains_key(&camera.id);
+        if exists {
+            issues.push(ImportIssue {
+                camera: Some(camera.id.clone()),
+                field: "id".to_string(),
+                message: "a camera with this id already exists, skipped".to_string(),
+            });
+        }
+        !exists
+    });
+    
+    import.dry_run = dry_run;
+    if dry_run {
+        return Ok(import);
+    }
+    
+    let imported_ids: Vec<String> = import.cameras.iter().map(|c| c.id.clone()).collect();
+    if !import.cameras.is_empty() {
+        let reconnect_policy = import.config.as_ref().unwrap_or(&base).reconnect_policy.clone();
+        crate::cameras::insert_cameras(&mut cameras, &import.cameras, &reconnect_policy, Some(app_handle.clone()))
+            .map_err(|e| format!("{:#}", e))?;
+        if let Err(e) = save_cameras_config(&cameras, &cameras_path) {
+            let added = remove_imported_cameras(&mut cameras, &imported_ids);
+            drop(cameras);
+            shutdown_imported_cameras(added, &base.shutdown_policy).await;
+            return Err(format!("{:#}", e));
+        }
+    }
+    
+    // config.json solo se toca si las cámaras quedaron guardadas; si falla se
+    // deshace el import de cámaras
+    if let Some(config) = &import.config {
+        if let Err(e) = crate::config_manager::save_agent_config(&config_dir.join("config.json"), config) {
+            let added = remove_imported_cameras(&mut cameras, &imported_ids);
+            if !added.is_empty() {
+                if let Err(e) = save_cameras_config(&cameras, &cameras_path) {
+                    log::error!("Failed to roll back imported cameras: {:#}", e);
+                }
+            }
+            drop(cameras);
+            shutdown_imported_cameras(added, &base.shutdown_policy).await;
+            return Err(format!("{:#}", e));
+        }
+    }
+    drop(cameras);
+    
+    if let Some(config) = &import.config {
+        *state.config.lock().await = config.clone();
+        app_handle.emit("agent-config-updated", config).map_err(|e| e.to_string())?;
+    }
+    if !imported_ids.is_empty() {
+        app_handle.emit("cameras-updated", ()).map_err(|e| e.to_string())?;
+    }
+    
+    log::info!(
+        "Imported {} cameras from legacy config ({} issues)",
+        import.cameras.len(), import.issues.len()
+    );
+    Ok(import)
+}
+
+/// Saca del mapa las cámaras recién importadas (rollback de un import)
+fn remove_imported_cameras(
+    cameras: &mut HashMap<String, CameraHandle>,
+    ids: &[String],
+) -> Vec<CameraHandle> {
+    ids.iter().filter_map(|id| cameras.remove(id)).collect()
+}
+
+/// Termina las tareas de las cámaras quitadas por el rollback
+async fn shutdown_imported_cameras(cameras: Vec<CameraHandle>, shutdown: &ShutdownPolicy) {
+    for camera in cameras {
+        if let Err(e) = camera.shutdown(shutdown).await {
+            log::warn!("Failed to stop imported camera: {:#}", e);
+        }
+    }
+}
+
+/// Versiones y capacidades de FFmpeg, MediaMTX y cloudflared. Con `refresh`
+/// vuelve a consultar los binarios en lugar de usar lo guardado.
+#[tauri::command]
+pub async fn get_capabilities(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+    refresh: Option<bool>,
+) -> Result<ToolCapabilities, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some(capabilities) = state.capabilities.lock().await.clone() {
+            return Ok(capabilities);
+        }
+    }
+    refresh_capabilities(state.inner(), &app_handle).await
+}
+
+/// Consulta las herramientas instaladas y guarda el resultado en el estado
+pub async fn refresh_capabilities(
+    state: &AppState,
+    app_handle: &tauri::AppHandle,
+) -> Result<ToolCapabilities, String> {
+    let binary_paths = state.config.lock().await.binary_paths.clone();
+    let resolver = BinaryResolver::from_app(app_handle, binary_paths).map_err(|e| e.to_string())?;
+    let capabilities = probe_tools(&resolver).await;
+    *state.capabilities.lock().await = Some(capabilities.clone());
+    Ok(capabilities)
+}
+
+/// Lo necesario para lanzar FFmpeg, con el binario resuelto según
+/// `config.json` y sus capacidades (consultadas si todavía no se hizo)
+async fn camera_launch(state: &AppState, app_handle: &tauri::AppHandle) -> Result<LaunchContext, String> {
+    let binary_paths = state.config.lock().await.binary_paths.clone();
+    let ffmpeg = BinaryResolver::from_app(app_handle, binary_paths)
+        .and_then(|resolver| resolver.resolve(FFMPEG))
+        .map_err(|e| format!("FFmpeg binary not found: {}", e))?;
     
-    Ok(format!("Camera {} reconnected successfully", id))
+    let cached = state.capabilities.lock().await.clone();
+    let capabilities = match cached {
+        Some(capabilities) => Some(capabilities),
+        None => refresh_capabilities(state, app_handle).await.ok(),
+    };
+    
+    Ok(LaunchContext {
+        launcher: state.launcher.clone(),
+        ffmpeg_path: ffmpeg.path,
+        sink: LogSink::new(state.logs.clone(), Some(app_handle.clone())),
+        ffmpeg_capabilities: capabilities.and_then(|c| c.ffmpeg),
+    })
 }
 
 /// Obtiene logs de un componente
@@ -382,15 +714,3 @@ pub async fn get_logs(
     let lines_to_get = lines.unwrap_or(100);
     Ok(logs.get_last(&component, lines_to_get))
 }
-
-// Payloads para eventos
-#[derive(Clone, serde::Serialize)]
-struct AgentStatusPayload {
-    running: bool,
-}
-
-#[derive(Clone, serde::Serialize)]
-struct CameraStatusPayload {
-    id: String,
-    status: String,
-}
@@ -1,5 +1,13 @@
+use crate::app_state::{
+    AgentConfig, AudioMode, CameraConfig, CamerasConfigFile, EncodingMode, ImportIssue, LegacyFormat,
+    LegacyImport, QualityPreset, TunnelMode,
+};
+use crate::supervisor::BinaryResolver;
 use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use tauri::Manager;
 
@@ -81,17 +89,10 @@ impl ConfigManager {
     /// Crea un archivo de config por defecto
     fn create_default_config(&self, file_name: &str, target_path: &Path) -> Result<()> {
         let content = match file_name {
-            "cameras.json" => r#"{
-  "cameras": []
-}"#,
-            "config.json" => r#"{
-  "autoStartAgent": false,
-  "autoStartCameras": true,
-  "tunnelEnabled": false,
-  "tunnelMode": "quick"
-}"#,
-            "mediamtx.yml" => include_str!("../config/mediamtx.yml"),
-            _ => "",
+            "cameras.json" => serde_json::to_string_pretty(&CamerasConfigFile::default())?,
+            "config.json" => serde_json::to_string_pretty(&AgentConfig::default())?,
+            "mediamtx.yml" => include_str!("../config/mediamtx.yml").to_string(),
+            _ => String::new(),
         };
         
         fs::write(target_path, content)
@@ -106,22 +107,554 @@ impl ConfigManager {
         self.config_dir.join(file_name)
     }
     
-    /// Obtiene la ruta de un binario
-    pub fn get_binary_path(&self, binary_name: &str) -> Result<PathBuf> {
-        let bin_path = self.resource_dir.join("bin").join(binary_name);
-        
-        if !bin_path.exists() {
-            // En desarrollo, buscar en src-tauri/bin
-            let dev_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("bin").join(binary_name);
-            if dev_path.exists() {
-                log::info!("Using dev binary: {:?}", dev_path);
-                return Ok(dev_path);
+    /// Resolver de binarios sobre los resources de la app y las rutas
+    /// configuradas
+    pub fn binary_resolver(&self, overrides: HashMap<String, PathBuf>) -> BinaryResolver {
+        BinaryResolver::new(self.resource_dir.clone(), overrides)
+    }
+}
+
+/// Carga `config.json`. Si no existe se usa la configuración por defecto;
+/// los campos que no pasan la validación vuelven a su default con un aviso
+/// para que un valor inválido no impida arrancar el agente.
+pub fn load_agent_config(config_path: &Path) -> Result<AgentConfig> {
+    let content = match fs::read_to_string(config_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            log::info!("No agent config at {:?}, using defaults", config_path);
+            return Ok(AgentConfig::default());
+        }
+        Err(e) => return Err(e).context("Failed to read agent config"),
+    };
+
+    let migrated = migrate_file(config_path, &content, CONFIG_MIGRATIONS)?;
+    let mut config: AgentConfig = serde_json::from_value(migrated)
+        .context("Failed to parse agent config")?;
+
+    for error in config.validate() {
+        log::warn!(
+            "Invalid {} in agent config ({}), using default",
+            error.field, error.message
+        );
+        config.reset_field(&error.field);
+    }
+
+    Ok(config)
+}
+
+/// Paso de migración de un archivo de configuración de la versión N a la N+1.
+/// Trabaja sobre el JSON crudo para poder leer campos que ya no existen en
+/// los structs actuales.
+pub type Migration = fn(&mut Value) -> Result<()>;
+
+/// Migraciones de `cameras.json`; la posición N migra de la versión N a la N+1
+pub const CAMERAS_MIGRATIONS: &[Migration] = &[cameras_v0_to_v1];
+
+/// Migraciones de `config.json`; la posición N migra de la versión N a la N+1
+pub const CONFIG_MIGRATIONS: &[Migration] = &[config_v0_to_v1];
+
+/// Lleva el contenido de un archivo de configuración a la última versión de
+/// su esquema. Si hubo que migrar, guarda el original como
+/// `<archivo>.v<versión>.bak` y reescribe el archivo migrado.
+pub fn migrate_file(path: &Path, content: &str, migrations: &[Migration]) -> Result<Value> {
+    let mut value: Value = serde_json::from_str(content)
+        .with_context(|| format!("Failed to parse {:?}", path))?;
+    let from = migrate(&mut value, migrations)
+        .with_context(|| format!("Failed to migrate {:?}", path))?;
+
+    if from < migrations.len() as u32 {
+        let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+        backup_name.push(format!(".v{}.bak", from));
+        let backup_path = path.with_file_name(backup_name);
+        // No pisar el backup de una migración anterior que falló al guardar
+        if !backup_path.exists() {
+            fs::write(&backup_path, content)
+                .with_context(|| format!("Failed to back up {:?}", path))?;
+        }
+
+        let migrated = serde_json::to_string_pretty(&value)?;
+        write_atomic(path, migrated.as_bytes())
+            .with_context(|| format!("Failed to save migrated {:?}", path))?;
+        log::info!(
+            "Migrated {:?} from schema v{} to v{} (original saved as {:?})",
+            path, from, migrations.len(), backup_path
+        );
+    }
+
+    Ok(value)
+}
+
+/// Archivo escrito por una versión más nueva del agente. No es un archivo
+/// dañado: no se toca ni se reemplaza por un backup.
+#[derive(Debug, thiserror::Error)]
+#[error("schemaVersion {found} is newer than this agent supports ({supported}), it was written by a newer version")]
+pub struct NewerSchemaVersion {
+    pub found: u32,
+    pub supported: u32,
+}
+
+/// Falla con `NewerSchemaVersion` si el archivo en `path` lo escribió una
+/// versión más nueva del agente: guardar encima perdería lo que esta versión
+/// no entiende. Un archivo que no existe o no se puede leer como JSON se
+/// puede reemplazar.
+pub fn ensure_not_newer(path: &Path, migrations: &[Migration]) -> Result<()> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    let found = serde_json::from_slice::<Value>(&content).ok()
+        .and_then(|value| value.get("schemaVersion").and_then(Value::as_u64))
+        .unwrap_or(0);
+    let supported = migrations.len() as u32;
+    if found > u64::from(supported) {
+        let found = u32::try_from(found).unwrap_or(u32::MAX);
+        return Err(NewerSchemaVersion { found, supported }.into());
+    }
+    Ok(())
+}
+
+/// Aplica las migraciones pendientes según `schemaVersion` (sin el campo es
+/// la versión 0) y devuelve la versión original
+pub fn migrate(value: &mut Value, migrations: &[Migration]) -> Result<u32> {
+    let current = migrations.len() as u32;
+    let from = match value.as_object().map(|object| object.get("schemaVersion")) {
+        None => anyhow::bail!("Config file is not a JSON object"),
+        Some(None) => 0,
+        Some(Some(version)) => version.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid schemaVersion: {}", version))?,
+    };
+    if from > current {
+        return Err(NewerSchemaVersion { found: from, supported: current }.into());
+    }
+
+    for (version, migration) in migrations.iter().enumerate().skip(from as usize) {
+        migration(value).with_context(|| format!("Migration from schemaVersion {} failed", version))?;
+        value["schemaVersion"] = Value::from(version as u32 + 1);
+    }
+    Ok(from)
+}
+
+/// v0 → v1: archivos sin versión. `encoding`, `quality` y `audioMode` pasan a
+/// escribirse siempre; se completan con los valores que antes ponía serde.
+fn cameras_v0_to_v1(value: &mut Value) -> Result<()> {
+    let cameras = value.get_mut("cameras")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| anyhow::anyhow!("Missing cameras list"))?;
+    for camera in cameras {
+        let camera = camera.as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("Camera entry is not a JSON object"))?;
+        camera.entry("encoding").or_insert_with(|| "copy".into());
+        camera.entry("quality").or_insert_with(|| "medium".into());
+        camera.entry("audioMode").or_insert_with(|| "copy".into());
+    }
+    Ok(())
+}
+
+/// v0 → v1: archivos sin versión. El `config.json` que generaba la app traía
+/// `tunnelEnabled: false` y `tunnelMode: "quick"` sin que se usaran (el túnel
+/// arrancaba igual, con nombre si lo había). Se quitan para que esas
+/// instalaciones sigan con el túnel encendido en modo automático.
+fn config_v0_to_v1(value: &mut Value) -> Result<()> {
+    let config = value.as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("Config is not a JSON object"))?;
+    config.remove("tunnelEnabled");
+    config.remove("tunnelMode");
+    Ok(())
+}
+
+/// Guarda la configuración completa en `config.json` de forma atómica
+pub fn save_agent_config(config_path: &Path, config: &AgentConfig) -> Result<()> {
+    ensure_not_newer(config_path, CONFIG_MIGRATIONS)
+        .context("Refusing to overwrite agent config")?;
+    let content = serde_json::to_string_pretty(config)
+        .context("Failed to serialize agent config")?;
+    write_atomic(config_path, content.as_bytes())
+        .context("Failed to write agent config")?;
+
+    log::info!("Saved agent config to {:?}", config_path);
+    Ok(())
+}
+
+/// Backups rotativos que se conservan de `cameras.json`
+pub const CAMERAS_BACKUPS: usize = 5;
+
+/// Ruta del backup número `n` de un archivo (1 es el más reciente):
+/// `cameras.json.bak.1`
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{}", n));
+    path.with_file_name(name)
+}
+
+/// Backups existentes de un archivo, del más reciente al más viejo
+pub fn list_backups(path: &Path, keep: usize) -> Vec<PathBuf> {
+    (1..=keep)
+        .map(|n| backup_path(path, n))
+        .filter(|backup| backup.exists())
+        .collect()
+}
+
+/// Escribe un archivo JSON de forma atómica guardando antes el contenido
+/// actual como backup más reciente y rotando los anteriores (se conservan
+/// `keep`). Un archivo actual que no es JSON válido no se respalda, para no
+/// desplazar los backups buenos.
+pub fn write_with_backups(path: &Path, content: &[u8], keep: usize) -> Result<()> {
+    let current = match fs::read(path) {
+        Ok(current) => Some(current),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+
+    match current {
+        Some(current) if keep == 0 || current == content => {}
+        Some(current) if serde_json::from_slice::<Value>(&current).is_ok() => {
+            for n in (1..keep).rev() {
+                let from = backup_path(path, n);
+                if from.exists() {
+                    fs::rename(&from, backup_path(path, n + 1))
+                        .with_context(|| format!("Failed to rotate backup {:?}", from))?;
+                }
             }
-            return Err(anyhow::anyhow!("Binary not found: {} (checked {:?} and {:?})", 
-                binary_name, bin_path, dev_path));
+            write_atomic(&backup_path(path, 1), &current)?;
         }
-        
-        Ok(bin_path)
+        Some(_) => log::warn!("Not backing up unreadable {:?}", path),
+        None => {}
+    }
+    write_atomic(path, content)
+}
+
+/// Escribe un archivo sin dejarlo a medias: escribe en un temporal junto al
+/// destino, lo sincroniza a disco y lo renombra encima del original
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let file_name = path.file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid path: {:?}", path))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)
+        .with_context(|| format!("Failed to create {:?}", tmp_path))?;
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("Failed to write {:?}", tmp_path))?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+/// Importa `cameras.json` y/o `config.json` de stream-agent-electron o del
+/// servidor Node (server-completo-frp). Detecta el formato de cada archivo,
+/// lo traslada a `CameraConfig` / `AgentConfig` (partiendo de `base`) y anota
+/// en `issues` lo que no se pudo trasladar. No escribe nada.
+pub fn import_legacy(
+    cameras_json: Option<&str>,
+    config_json: Option<&str>,
+    base: &AgentConfig,
+) -> Result<LegacyImport> {
+    let mut import = LegacyImport {
+        cameras_format: None,
+        config_format: None,
+        cameras: Vec::new(),
+        config: None,
+        issues: Vec::new(),
+        dry_run: true,
+    };
+    let mut config = base.clone();
+    let mut config_changed = false;
+
+    if let Some(content) = cameras_json {
+        let value: Value = serde_json::from_str(content)
+            .context("Failed to parse legacy cameras file")?;
+        let (format, location) = import_legacy_cameras(&value, &mut import)?;
+        import.cameras_format = Some(format);
+
+        // Los registros del servidor Node traen la ubicación de cada cámara
+        if let Some((location_id, location_name)) = location {
+            apply_legacy_field(&mut config, "locationId", &mut import.issues, |c| c.location_id = location_id);
+            apply_legacy_field(&mut config, "locationName", &mut import.issues, |c| c.location_name = location_name);
+            config_changed = true;
+        }
+    }
+
+    if let Some(content) = config_json {
+        let value: Value = serde_json::from_str(content)
+            .context("Failed to parse legacy config file")?;
+        import_electron_config(&value, &mut config, &mut import.issues)?;
+        import.config_format = Some(LegacyFormat::ElectronConfig);
+        config_changed = true;
+    }
+
+    if config_changed {
+        import.config = Some(config);
+    }
+    Ok(import)
+}
+
+/// Cámaras de cualquiera de los formatos viejos de `cameras.json`. Devuelve
+/// el formato detectado y la ubicación, si el archivo la trae.
+fn import_legacy_cameras(
+    value: &Value,
+    import: &mut LegacyImport,
+) -> Result<(LegacyFormat, Option<(String, String)>)> {
+    let object = value.as_object()
+        .ok_or_else(|| anyhow::anyhow!("Legacy cameras file is not a JSON object"))?;
+
+    // stream-agent-electron: { "cameras": [ { id, name, rtspUrl, ... } ] }
+    if let Some(cameras) = object.get("cameras") {
+        let cameras = cameras.as_array()
+            .ok_or_else(|| anyhow::anyhow!("\"cameras\" is not a list"))?;
+        for key in object.keys().filter(|key| !["cameras", "schemaVersion"].contains(&key.as_str())) {
+            import.issues.push(import_issue(None, key, "not supported, ignored"));
+        }
+        for entry in cameras {
+            if let Some(camera) = import_electron_camera(entry, &mut import.issues) {
+                push_imported_camera(import, camera);
+            }
+        }
+        return Ok((LegacyFormat::ElectronCameras, None));
+    }
+
+    // Servidor Node: { "cam1": "rtsp://..." } en las versiones viejas,
+    // { "1-cam1": { publicUrl, locationId, ... } } desde /api/register.
+    // Un mismo archivo puede mezclar ambos.
+    let format = if object.values().all(Value::is_string) {
+        LegacyFormat::UrlMap
+    } else {
+        LegacyFormat::NodeRegistry
+    };
+    let mut locations: Vec<(String, String)> = Vec::new();
+    let mut ignored_fields: Vec<String> = Vec::new();
+
+    for (key, entry) in object {
+        let camera = match entry {
+            Value::String(url) => legacy_camera(key, key, "url", url, &mut import.issues),
+            Value::Object(record) => {
+                let location_id = record.get("locationId").and_then(json_text)
+                    .filter(|id| id != "unknown");
+                let location_name = record.get("locationName").and_then(json_text)
+                    .filter(|name| name != "Unknown Location");
+                if let (Some(id), Some(name)) = (&location_id, &location_name) {
+                    if !locations.contains(&(id.clone(), name.clone())) {
+                        locations.push((id.clone(), name.clone()));
+                    }
+                }
+
+                // Id local del agente; si no está, el del servidor sin el
+                // prefijo de ubicación ("1-cam1" -> "cam1")
+                let id = record.get("localCamId").and_then(json_text)
+                    .or_else(|| {
+                        location_id.as_ref()
+                            .and_then(|location| key.strip_prefix(&format!("{}-", location)))
+                            .map(str::to_string)
+                    })
+                    .unwrap_or_else(|| key.clone());
+
+                for field in record.keys() {
+                    let known = ["publicUrl", "locationId", "locationName", "localCamId", "camName"];
+                    if !known.contains(&field.as_str()) && !ignored_fields.contains(field) {
+                        ignored_fields.push(field.clone());
+                    }
+                }
+
+                match record.get("publicUrl").and_then(Value::as_str) {
+                    Some(url) => legacy_camera(key, &id, "publicUrl", url, &mut import.issues).map(|mut camera| {
+                        if let Some(name) = record.get("camName").and_then(json_text) {
+                            camera.name = name;
+                        }
+                        camera
+                    }),
+                    None => {
+                        import.issues.push(import_issue(Some(key), "publicUrl", "missing, camera skipped"));
+                        None
+                    }
+                }
+            }
+            _ => {
+                import.issues.push(import_issue(Some(key), "", "neither a URL nor a camera record, skipped"));
+                None
+            }
+        };
+        if let Some(camera) = camera {
+            push_imported_camera(import, camera);
+        }
+    }
+
+    // Datos que el servidor llevaba de cada registro (fechas, estado)
+    for field in ignored_fields {
+        import.issues.push(import_issue(None, &field, "server bookkeeping, not imported"));
+    }
+
+    if locations.len() > 1 {
+        let (id, name) = &locations[0];
+        import.issues.push(import_issue(
+            None,
+            "locationId",
+            &format!("cameras belong to {} locations, using {} ({})", locations.len(), name, id),
+        ));
+    }
+    Ok((format, locations.into_iter().next()))
+}
+
+/// Una cámara de `cameras.json` de stream-agent-electron
+fn import_electron_camera(entry: &Value, issues: &mut Vec<ImportIssue>) -> Option<CameraConfig> {
+    let Some(record) = entry.as_object() else {
+        issues.push(import_issue(None, "cameras", "entry is not a camera object, skipped"));
+        return None;
+    };
+    let Some(id) = record.get("id").and_then(json_text).filter(|id| !id.is_empty()) else {
+        issues.push(import_issue(None, "id", "camera without id, skipped"));
+        return None;
+    };
+    let Some(url) = record.get("rtspUrl").and_then(Value::as_str) else {
+        issues.push(import_issue(Some(&id), "rtspUrl", "missing, camera skipped"));
+        return None;
+    };
+    let mut camera = legacy_camera(&id, &id, "rtspUrl", url, issues)?;
+    // stream-agent-electron transcodificaba el audio si no se indicaba otra cosa
+    camera.audio_mode = AudioMode::Transcode;
+
+    for (field, value) in record {
+        match field.as_str() {
+            "id" | "rtspUrl" => {}
+            "name" => match json_text(value) {
+                Some(name) if !name.is_empty() => camera.name = name,
+                _ => issues.push(import_issue(Some(&id), field, "not a text, using the id")),
+            },
+            "enabled" => match value.as_bool() {
+                Some(enabled) => camera.enabled = enabled,
+                None => issues.push(import_issue(Some(&id), field, "not true/false, camera enabled")),
+            },
+            "encoding" => match serde_json::from_value(value.clone()) {
+                Ok(encoding) => camera.encoding = encoding,
+                Err(_) => issues.push(import_issue(Some(&id), field, &format!("unknown value {}, using copy", value))),
+            },
+            "quality" => match serde_json::from_value(value.clone()) {
+                Ok(quality) => camera.quality = quality,
+                Err(_) => issues.push(import_issue(Some(&id), field, &format!("unknown value {}, using medium", value))),
+            },
+            "audioMode" => match serde_json::from_value(value.clone()) {
+                Ok(audio_mode) => camera.audio_mode = audio_mode,
+                Err(_) => issues.push(import_issue(Some(&id), field, &format!("unknown value {}, using transcode", value))),
+            },
+            _ => issues.push(import_issue(Some(&id), field, "not supported, ignored")),
+        }
+    }
+    Some(camera)
+}
+
+/// Cámara habilitada con los valores por defecto, si `url` es RTSP. `key`
+/// identifica la entrada en el archivo viejo y `field` de dónde salió la URL.
+fn legacy_camera(
+    key: &str,
+    id: &str,
+    field: &str,
+    url: &str,
+    issues: &mut Vec<ImportIssue>,
+) -> Option<CameraConfig> {
+    if !(url.starts_with("rtsp://") || url.starts_with("rtsps://")) {
+        // En el servidor con Cloudflare, publicUrl es la URL HLS del agente
+        // viejo y no dice de qué cámara sale el video
+        issues.push(import_issue(
+            Some(key),
+            field,
+            &format!("{} is not an RTSP URL, add this camera by hand", url),
+        ));
+        return None;
+    }
+    Some(CameraConfig {
+        id: id.to_string(),
+        name: id.to_string(),
+        rtsp_url: url.to_string(),
+        enabled: true,
+        encoding: EncodingMode::Copy,
+        quality: QualityPreset::Medium,
+        audio_mode: AudioMode::Copy,
+        reconnect: None,
+    })
+}
+
+fn push_imported_camera(import: &mut LegacyImport, camera: CameraConfig) {
+    if import.cameras.iter().any(|c| c.id == camera.id) {
+        import.issues.push(import_issue(Some(&camera.id), "id", "duplicated in the file, skipped"));
+        return;
+    }
+    import.cameras.push(camera);
+}
+
+/// `config.json` de stream-agent-electron: ubicación, servidor y túnel
+fn import_electron_config(
+    value: &Value,
+    config: &mut AgentConfig,
+    issues: &mut Vec<ImportIssue>,
+) -> Result<()> {
+    let object = value.as_object()
+        .ok_or_else(|| anyhow::anyhow!("Legacy config file is not a JSON object"))?;
+
+    for (field, value) in object {
+        // Electron guardaba `null` en los datos de túnel sin configurar
+        let text = match json_text(value) {
+            Some(text) if text.is_empty() => {
+                issues.push(import_issue(None, field, "empty, ignored"));
+                continue;
+            }
+            Some(text) => text,
+            None if value.is_null() => continue,
+            None => {
+                issues.push(import_issue(None, field, "not a text, ignored"));
+                continue;
+            }
+        };
+        match field.as_str() {
+            "serverUrl" => apply_legacy_field(config, field, issues, |c| c.server_url = text),
+            "locationId" => apply_legacy_field(config, field, issues, |c| c.location_id = text),
+            "locationName" => apply_legacy_field(config, field, issues, |c| c.location_name = text),
+            "tunnelName" => apply_legacy_field(config, field, issues, |c| c.tunnel_name = text),
+            // Con un tunnelId, Electron usaba siempre ese túnel con nombre
+            "tunnelId" => apply_legacy_field(config, field, issues, |c| {
+                c.tunnel_id = Some(text);
+                c.tunnel_mode = TunnelMode::Named;
+            }),
+            "tunnelHostname" => apply_legacy_field(config, field, issues, |c| c.tunnel_hostname = Some(text)),
+            _ => issues.push(import_issue(None, field, "not supported, ignored")),
+        }
+    }
+    Ok(())
+}
+
+/// Aplica un valor importado solo si el campo sigue siendo válido; si no,
+/// conserva el valor actual y lo informa
+fn apply_legacy_field(
+    config: &mut AgentConfig,
+    field: &str,
+    issues: &mut Vec<ImportIssue>,
+    apply: impl FnOnce(&mut AgentConfig),
+) {
+    let mut candidate = config.clone();
+    apply(&mut candidate);
+    match candidate.validate().into_iter().find(|error| error.field == field) {
+        Some(error) => issues.push(import_issue(None, field, &format!("{}, keeping the current value", error.message))),
+        None => *config = candidate,
+    }
+}
+
+/// Texto de un valor JSON; los números también valen (ids de ubicación)
+fn json_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn import_issue(camera: Option<&str>, field: &str, message: &str) -> ImportIssue {
+    ImportIssue {
+        camera: camera.map(str::to_string),
+        field: field.to_string(),
+        message: message.to_string(),
     }
 }
 
@@ -214,3 +747,290 @@ credentials-file: {}
     log::info!("Generated cloudflared config at {:?}", output_path);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_state::*;
+
+    /// Migra una fixture en memoria y la deserializa como la versión actual
+    fn migrate_fixture<T: serde::de::DeserializeOwned>(content: &str, migrations: &[Migration]) -> (u32, T) {
+        let mut value: Value = serde_json::from_str(content).unwrap();
+        let from = migrate(&mut value, migrations).unwrap();
+        assert_eq!(value["schemaVersion"], migrations.len());
+        (from, serde_json::from_value(value).unwrap())
+    }
+
+    fn temp_config(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stream-agent-config-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("config.json")
+    }
+
+    #[test]
+    fn test_load_agent_config_resets_invalid_fields() {
+        let path = temp_config("load");
+        assert_eq!(load_agent_config(&path).unwrap().location_id, "1");
+
+        fs::write(&path, r#"{
+  "schemaVersion": 1,
+  "serverUrl": "ftp://example.com",
+  "locationId": "42",
+  "locationName": "Club Norte",
+  "tunnelMode": "quick",
+  "readinessPolicy": { "timeoutMs": 100, "intervalMs": 500 }
+}"#).unwrap();
+        let config = load_agent_config(&path).unwrap();
+        assert_eq!(config.location_id, "42");
+        assert_eq!(config.location_name, "Club Norte");
+        assert_eq!(config.tunnel_mode, TunnelMode::Quick);
+        assert_eq!(config.server_url, AgentConfig::default().server_url);
+        assert_eq!(config.readiness_policy.timeout_ms, 15000);
+        assert!(config.validate().is_empty());
+
+        save_agent_config(&path, &config).unwrap();
+        let saved = load_agent_config(&path).unwrap();
+        assert_eq!(saved.location_name, "Club Norte");
+        assert!(!path.with_file_name("config.json.tmp").exists());
+
+        // Un archivo de una versión más nueva no se pisa
+        let newer = r#"{"schemaVersion": 99, "locationId": "7"}"#;
+        fs::write(&path, newer).unwrap();
+        let error = save_agent_config(&path, &config).unwrap_err();
+        assert!(error.downcast_ref::<NewerSchemaVersion>().is_some(), "{:#}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_validate_reports_fields() {
+        let mut config = AgentConfig::default();
+        config.location_name = " ".to_string();
+        config.tunnel_hostname = Some("https://cams.example.com/".to_string());
+        config.binary_paths.insert("ffmpeg".to_string(), PathBuf::from("bin/ffmpeg"));
+
+        let fields: Vec<String> = config.validate().into_iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["locationName", "tunnelHostname", "binaryPaths"]);
+    }
+
+    #[test]
+    fn test_schema_versions_match_migrations() {
+        assert_eq!(CAMERAS_MIGRATIONS.len() as u32, CAMERAS_SCHEMA_VERSION);
+        assert_eq!(CONFIG_MIGRATIONS.len() as u32, CONFIG_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_cameras_fixtures() {
+        let (from, file): (_, CamerasConfigFile) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/cameras_v0_baseline.json"),
+            CAMERAS_MIGRATIONS,
+        );
+        assert_eq!(from, 0);
+        assert_eq!(file.cameras.len(), 2);
+        assert_eq!(file.cameras[1].encoding, EncodingMode::Transcode);
+        assert_eq!(file.cameras[1].quality, QualityPreset::High);
+
+        let (_, file): (_, CamerasConfigFile) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/cameras_v0_minimal.json"),
+            CAMERAS_MIGRATIONS,
+        );
+        let camera = &file.cameras[0];
+        assert_eq!(camera.id, "cancha1");
+        assert_eq!(
+            (&camera.encoding, &camera.quality, &camera.audio_mode),
+            (&EncodingMode::Copy, &QualityPreset::Medium, &AudioMode::Copy)
+        );
+
+        let (_, file): (_, CamerasConfigFile) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/cameras_v0_reconnect.json"),
+            CAMERAS_MIGRATIONS,
+        );
+        assert_eq!(file.cameras[0].audio_mode, AudioMode::Disabled);
+        assert_eq!(file.cameras[0].reconnect.as_ref().unwrap().max_retries, 5);
+
+        let (from, file): (_, CamerasConfigFile) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/cameras_v1.json"),
+            CAMERAS_MIGRATIONS,
+        );
+        assert_eq!(from, 1);
+        assert_eq!(file.schema_version, CAMERAS_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_config_fixtures() {
+        let (from, config): (_, AgentConfig) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/config_v0_baseline.json"),
+            CONFIG_MIGRATIONS,
+        );
+        assert_eq!(from, 0);
+        assert_eq!(config.location_name, "Ubicación Principal");
+        assert!(config.validate().is_empty());
+
+        // Los placeholders del default generado no apagan el túnel
+        let (_, config): (_, AgentConfig) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/config_v0_generated.json"),
+            CONFIG_MIGRATIONS,
+        );
+        assert!(config.tunnel_enabled);
+        assert_eq!(config.tunnel_mode, TunnelMode::Auto);
+        assert!(config.auto_start_cameras);
+
+        let (_, config): (_, AgentConfig) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/config_v0_reconnect_binaries.json"),
+            CONFIG_MIGRATIONS,
+        );
+        assert_eq!(config.location_id, "7");
+        assert_eq!(config.reconnect_policy.max_retries, 3);
+        assert_eq!(config.binary_paths["ffmpeg"], PathBuf::from("/usr/local/bin/ffmpeg"));
+
+        // Desde v1 los valores del túnel se respetan
+        let (from, config): (_, AgentConfig) = migrate_fixture(
+            include_str!("../tests/fixtures/migrations/config_v1.json"),
+            CONFIG_MIGRATIONS,
+        );
+        assert_eq!(from, 1);
+        assert!(!config.tunnel_enabled);
+        assert_eq!(config.tunnel_mode, TunnelMode::Named);
+        assert_eq!(config.tunnel_hostname.as_deref(), Some("norte.example.com"));
+    }
+
+    #[test]
+    fn test_migrate_file_backs_up_original() {
+        let path = temp_config("migrate");
+        let original = include_str!("../tests/fixtures/migrations/config_v0_generated.json");
+        fs::write(&path, original).unwrap();
+
+        let config = load_agent_config(&path).unwrap();
+        assert!(config.tunnel_enabled);
+        assert_eq!(fs::read_to_string(path.with_file_name("config.json.v0.bak")).unwrap(), original);
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["schemaVersion"], CONFIG_SCHEMA_VERSION);
+        assert!(saved.get("tunnelEnabled").is_none());
+
+        // Un archivo de una versión más nueva no se toca
+        fs::write(&path, r#"{ "schemaVersion": 99 }"#).unwrap();
+        let error = load_agent_config(&path).unwrap_err();
+        assert!(format!("{:#}", error).contains("newer"), "{:#}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{ "schemaVersion": 99 }"#);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_import_electron_files() {
+        let import = import_legacy(
+            Some(include_str!("../tests/fixtures/legacy/electron_cameras.json")),
+            Some(include_str!("../tests/fixtures/legacy/electron_config.json")),
+            &AgentConfig::default(),
+        ).unwrap();
+
+        assert_eq!(import.cameras_format, Some(LegacyFormat::ElectronCameras));
+        assert_eq!(import.config_format, Some(LegacyFormat::ElectronConfig));
+        assert_eq!(import.cameras.len(), 2);
+        // Sin audioMode, Electron transcodificaba el audio
+        assert_eq!(import.cameras[0].audio_mode, AudioMode::Transcode);
+        assert_eq!(import.cameras[1].encoding, EncodingMode::Transcode);
+
+        let config = import.config.unwrap();
+        assert_eq!(config.location_name, "Club Centro");
+        assert_eq!(config.tunnel_mode, TunnelMode::Named);
+        assert_eq!(config.tunnel_id.as_deref(), Some("8c04466a-28f3-447d-857e-9dce8c890d1b"));
+        assert_eq!(config.tunnel_hostname.as_deref(), Some("tuneluno.noaservice.org"));
+        assert!(import.issues.is_empty(), "{:?}", import.issues);
+    }
+
+    #[test]
+    fn test_import_node_server_files() {
+        let import = import_legacy(
+            Some(include_str!("../tests/fixtures/legacy/node_url_map.json")),
+            None,
+            &AgentConfig::default(),
+        ).unwrap();
+        assert_eq!(import.cameras_format, Some(LegacyFormat::UrlMap));
+        assert_eq!(import.cameras.len(), 2);
+        assert!(import.config.is_none());
+
+        let import = import_legacy(
+            Some(include_str!("../tests/fixtures/legacy/node_registry.json")),
+            None,
+            &AgentConfig::default(),
+        ).unwrap();
+        assert_eq!(import.cameras_format, Some(LegacyFormat::NodeRegistry));
+
+        let mut ids: Vec<&str> = import.cameras.iter().map(|c| c.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["cam0", "cam1", "cam2"]);
+        let cam2 = import.cameras.iter().find(|c| c.id == "cam2").unwrap();
+        assert_eq!(cam2.name, "Cancha 2");
+        assert_eq!(cam2.rtsp_url, "rtsp://frp.noaservice.org:7002/cam2");
+
+        let config = import.config.unwrap();
+        assert_eq!((config.location_id.as_str(), config.location_name.as_str()), ("2", "Club Oeste"));
+
+        // La URL HLS no sirve como origen; las fechas y el estado no se importan
+        let mut fields: Vec<&str> = import.issues.iter().map(|i| i.field.as_str()).collect();
+        fields.sort();
+        assert_eq!(fields, vec!["lastUpdate", "publicUrl", "registeredAt", "status"]);
+        let skipped = import.issues.iter().find(|i| i.field == "publicUrl").unwrap();
+        assert_eq!(skipped.camera.as_deref(), Some("2-cam3"));
+    }
+
+    #[test]
+    fn test_import_reports_invalid_values() {
+        let mut base = AgentConfig::default();
+        base.location_name = "Actual".to_string();
+
+        let import = import_legacy(
+            Some(r#"{ "cameras": [
+                { "id": "a", "rtspUrl": "rtsp://10.0.0.1/a", "quality": "ultra", "fps": 30 },
+                { "id": "a", "rtspUrl": "rtsp://10.0.0.1/b" },
+                { "name": "sin id", "rtspUrl": "rtsp://10.0.0.1/c" }
+            ] }"#),
+            Some(r#"{ "serverUrl": "padel.local", "locationName": "", "theme": "dark" }"#),
+            &base,
+        ).unwrap();
+
+        assert_eq!(import.cameras.len(), 1);
+        assert_eq!(import.cameras[0].quality, QualityPreset::Medium);
+        let config = import.config.unwrap();
+        assert_eq!(config.server_url, AgentConfig::default().server_url);
+        assert_eq!(config.location_name, "Actual");
+
+        let issues: Vec<(Option<&str>, &str)> = import.issues.iter()
+            .map(|i| (i.camera.as_deref(), i.field.as_str()))
+            .collect();
+        assert_eq!(issues, vec![
+            (Some("a"), "fps"),
+            (Some("a"), "quality"),
+            (Some("a"), "id"),
+            (None, "id"),
+            (None, "locationName"),
+            (None, "serverUrl"),
+            (None, "theme"),
+        ]);
+
+        assert!(import_legacy(Some("[1, 2]"), None, &base).is_err());
+    }
+
+    #[test]
+    fn test_write_with_backups_rotates() {
+        let path = temp_config("backups").with_file_name("cameras.json");
+        for n in 1..=5 {
+            write_with_backups(&path, format!("{{\"n\": {}}}", n).as_bytes(), 3).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"n": 5}"#);
+        let backups: Vec<String> = list_backups(&path, 3).iter()
+            .map(|backup| fs::read_to_string(backup).unwrap())
+            .collect();
+        assert_eq!(backups, vec![r#"{"n": 4}"#, r#"{"n": 3}"#, r#"{"n": 2}"#]);
+        assert!(!backup_path(&path, 4).exists());
+
+        // Un archivo truncado no desplaza a los backups buenos
+        fs::write(&path, r#"{"n": 6"#).unwrap();
+        write_with_backups(&path, br#"{"n": 7}"#, 3).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), r#"{"n": 4}"#);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
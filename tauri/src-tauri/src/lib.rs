@@ -1,15 +1,31 @@
+mod agent;
 mod app_state;
 mod cameras;
 mod commands;
 mod config_manager;
 mod supervisor;
 
-use app_state::AppState;
+use app_state::{AgentConfig, AppState};
 use config_manager::ConfigManager;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tauri::{Manager, Wry};
 use tauri::menu::{MenuBuilder, MenuItemBuilder};
 use tauri::tray::{TrayIconBuilder, TrayIconEvent};
 
+/// Id del ícono del tray, para actualizar su tooltip
+const TRAY_ID: &str = "main";
+
+/// Margen al cerrar la aplicación, sobre lo que permiten las políticas, para
+/// el SIGKILL final y el resto del arranque o cierre en curso
+const SHUTDOWN_MARGIN: Duration = Duration::from_secs(5);
+
+/// Se activa al empezar el cierre, para no lanzarlo dos veces
+static SHUTDOWN_STARTED: AtomicBool = AtomicBool::new(false);
+/// Se activa cuando los procesos ya se detuvieron y la app puede salir
+static SHUTDOWN_DONE: AtomicBool = AtomicBool::new(false);
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Inicializar logger
@@ -23,6 +39,10 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .setup(|app| {
+            let mut agent_config = AgentConfig::default();
+            let mut config_warnings = Vec::new();
+            let mut orphan_cleanup = None;
+
             // Inicializar configuraciones (copiar defaults si no existen)
             match ConfigManager::new(&app.handle()) {
                 Ok(config_mgr) => {
@@ -31,7 +51,36 @@ pub fn run() {
                     } else {
                         log::info!("Configurations initialized successfully");
                     }
-                    
+
+                    let config_path = config_mgr.get_config_path("config.json");
+                    match config_manager::load_agent_config(&config_path) {
+                        Ok(config) => agent_config = config,
+                        Err(e) => {
+                            log::warn!("Using default agent config: {:#}", e);
+                            // Los cambios de configuración no se van a poder
+                            // guardar (ver `save_agent_config`); avisar en la UI
+                            if let Some(newer) = e.downcast_ref::<config_manager::NewerSchemaVersion>() {
+                                config_warnings.push(format!(
+                                    "config.json was written by a newer version of the agent (schemaVersion {}), using defaults; settings changes cannot be saved",
+                                    newer.found
+                                ));
+                            }
+                        }
+                    }
+
+                    // Terminar procesos que quedaron de una ejecución anterior
+                    // (ocupan los puertos de MediaMTX) antes de lanzar nada.
+                    // Puede esperar varios segundos: fuera del hilo de la UI
+                    match supervisor::init_pid_dir(&config_mgr.config_dir) {
+                        Ok(()) => {
+                            let policy = agent_config.shutdown_policy.clone();
+                            orphan_cleanup = Some(tauri::async_runtime::spawn_blocking(move || {
+                                supervisor::cleanup_orphans(&policy)
+                            }));
+                        }
+                        Err(e) => log::error!("Failed to initialize PID files: {}", e),
+                    }
+
                     // Detectar tunnels existentes de cloudflared
                     if let Some(tunnel_info) = config_manager::detect_cloudflared_tunnel() {
                         log::info!("Cloudflared detected:");
@@ -50,12 +99,32 @@ pub fn run() {
             }
             
             // Inicializar estado global
-            let app_state = AppState::new();
+            let auto_start_agent = agent_config.auto_start_agent;
+            let mut app_state = AppState::new(agent_config);
+            app_state.config_warnings = Arc::new(tokio::sync::Mutex::new(config_warnings));
+            app_state.orphan_cleanup = Arc::new(tokio::sync::Mutex::new(orphan_cleanup));
             app.manage(app_state);
 
             // Configurar system tray
             setup_system_tray(app)?;
 
+            // Iniciar el agente si se pidió (PCs que se reinician sin nadie
+            // presente); si no, solo consultar versiones y capacidades de los
+            // binarios. En ambos casos sin demorar la ventana.
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app_handle.state::<AppState>();
+                if auto_start_agent {
+                    log::info!("autoStartAgent enabled, starting agent");
+                    if let Err(e) = commands::launch_agent(state.inner(), &app_handle).await {
+                        log::error!("Failed to auto-start agent: {}", e);
+                        update_tray_tooltip(&app_handle, &format!("Stream Agent: auto-start failed - {}", e));
+                    }
+                } else if let Err(e) = commands::refresh_capabilities(state.inner(), &app_handle).await {
+                    log::warn!("Failed to probe tools: {}", e);
+                }
+            });
+
             log::info!("Stream Agent initialized successfully");
             Ok(())
         })
@@ -64,16 +133,98 @@ pub fn run() {
             commands::stop_agent,
             commands::get_agent_status,
             commands::list_cameras,
+            commands::get_camera_metrics,
+            commands::get_service_resources,
             commands::add_camera,
             commands::update_camera,
             commands::remove_camera,
             commands::start_camera,
             commands::stop_camera,
             commands::reconnect_camera,
+            commands::clear_camera_quarantine,
+            commands::get_reconnect_policy,
+            commands::set_reconnect_policy,
+            commands::get_agent_config,
+            commands::update_agent_config,
+            commands::import_legacy_config,
+            commands::get_capabilities,
             commands::get_logs,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(|window, event| {
+            // Cerrar la ventana principal la oculta en el tray; se sale desde "Quit"
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if window.label() == "main" {
+                    api.prevent_close();
+                    let _ = window.hide();
+                }
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::ExitRequested { code, api, .. } = event {
+                if SHUTDOWN_DONE.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                // Frenar la salida hasta detener los procesos hijos
+                api.prevent_exit();
+                if SHUTDOWN_STARTED.swap(true, Ordering::SeqCst) {
+                    return;
+                }
+
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    shutdown_on_exit(&app_handle).await;
+                    SHUTDOWN_DONE.store(true, Ordering::SeqCst);
+                    app_handle.exit(code.unwrap_or(0));
+                });
+            }
+        });
+}
+
+/// Detiene el agente antes de salir, con un tiempo máximo que incluye esperar
+/// un arranque en curso. Si no alcanza, los procesos que queden se limpian al
+/// próximo inicio mediante los PID files.
+async fn shutdown_on_exit(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    if state.lifecycle.lock().await.state == app_state::AgentState::Stopped {
+        return;
+    }
+
+    log::info!("Application exiting, stopping agent...");
+    let timeout = shutdown_timeout(&*state.config.lock().await);
+    match tokio::time::timeout(timeout, agent::shutdown_when_settled(state.inner(), Some(app_handle), "Application exiting")).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::error!("Error stopping agent on exit: {}", e),
+        Err(_) => log::error!(
+            "Agent did not stop within {}s, leftover processes will be cleaned up on next start",
+            timeout.as_secs()
+        ),
+    }
+}
+
+/// Tiempo máximo para detener el agente al salir según la configuración: un
+/// arranque en curso puede esperar la readiness de MediaMTX, y después se
+/// detienen las cámaras (en paralelo), Cloudflared y MediaMTX, uno tras otro
+fn shutdown_timeout(config: &AgentConfig) -> Duration {
+    Duration::from_millis(config.readiness_policy.timeout_ms)
+        + config.shutdown_policy.max_stop_time() * 3
+        + SHUTDOWN_MARGIN
+}
+
+/// Cambia el tooltip del ícono del tray (estado del agente, fallos).
+/// Windows corta los tooltips en 127 caracteres, así que se recorta antes.
+pub(crate) fn update_tray_tooltip(app_handle: &tauri::AppHandle, text: &str) {
+    let text: String = match text.char_indices().nth(120) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    };
+    if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
+        if let Err(e) = tray.set_tooltip(Some(text)) {
+            log::warn!("Failed to update tray tooltip: {}", e);
+        }
+    }
 }
 
 fn setup_system_tray(app: &mut tauri::App<Wry>) -> Result<(), Box<dyn std::error::Error>> {
@@ -86,8 +237,9 @@ fn setup_system_tray(app: &mut tauri::App<Wry>) -> Result<(), Box<dyn std::error
         .item(&quit_item)
         .build()?;
 
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .menu(&menu)
+        .tooltip("Stream Agent: stopped")
         .icon(app.default_window_icon().unwrap().clone())
         .on_menu_event(|app, event| match event.id().as_ref() {
             "show" => {
@@ -98,6 +250,7 @@ fn setup_system_tray(app: &mut tauri::App<Wry>) -> Result<(), Box<dyn std::error
             }
             "quit" => {
                 log::info!("Quitting application from tray");
+                // Los procesos se detienen en RunEvent::ExitRequested
                 app.exit(0);
             }
             _ => {}
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// Binarios que usa el agente (nombre lógico, sin extensión)
+pub const MEDIAMTX: &str = "mediamtx";
+pub const FFMPEG: &str = "ffmpeg";
+pub const CLOUDFLARED: &str = "cloudflared";
+
+/// Dónde se encontró un binario
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BinarySource {
+    /// `bin/` dentro de los resources de la app
+    Bundled,
+    /// `src-tauri/bin` al correr en desarrollo (solo builds de debug)
+    Development,
+    /// Ruta explícita en `binaryPaths` de `config.json`
+    ConfigOverride,
+    /// Encontrado en el `PATH` del sistema
+    SystemPath,
+}
+
+/// Binario resuelto, para lanzarlo e informar en la UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedBinary {
+    pub name: String,
+    pub path: PathBuf,
+    pub source: BinarySource,
+}
+
+/// Busca los binarios en orden: resources de la app, rutas de `config.json`
+/// y por último el `PATH`
+#[derive(Debug, Clone)]
+pub struct BinaryResolver {
+    resource_dir: PathBuf,
+    overrides: HashMap<String, PathBuf>,
+    /// Directorios del `PATH` del sistema
+    path_dirs: Vec<PathBuf>,
+}
+
+impl BinaryResolver {
+    pub fn new(resource_dir: PathBuf, overrides: HashMap<String, PathBuf>) -> Self {
+        let path_dirs = std::env::var_os("PATH")
+            .map(|paths| std::env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        Self { resource_dir, overrides, path_dirs }
+    }
+
+    pub fn from_app(app_handle: &tauri::AppHandle, overrides: HashMap<String, PathBuf>) -> Result<Self> {
+        let resource_dir = app_handle
+            .path()
+            .resource_dir()
+            .context("Failed to get resource dir")?;
+        Ok(Self::new(resource_dir, overrides))
+    }
+
+    /// Resuelve un binario por nombre lógico (`"ffmpeg"`), agregando la
+    /// extensión de la plataforma
+    pub fn resolve(&self, name: &str) -> Result<ResolvedBinary> {
+        let file_name = binary_file_name(name);
+        let found = |path: PathBuf, source: BinarySource| {
+            log::debug!("Resolved {} from {:?}: {:?}", name, source, path);
+            Ok(ResolvedBinary { name: name.to_string(), path, source })
+        };
+
+        let bundled = self.resource_dir.join("bin").join(&file_name);
+        if is_executable(&bundled) {
+            return found(bundled, BinarySource::Bundled);
+        }
+
+        // En un build de release CARGO_MANIFEST_DIR es el árbol de fuentes de
+        // la máquina que compiló, no tiene sentido buscar ahí
+        let dev = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("bin").join(&file_name);
+        if cfg!(debug_assertions) && is_executable(&dev) {
+            return found(dev, BinarySource::Development);
+        }
+
+        if let Some(path) = self.overrides.get(name) {
+            if is_executable(path) {
+                return found(path.clone(), BinarySource::ConfigOverride);
+            }
+            log::warn!("Configured path for {} is not an executable file: {:?}", name, path);
+        }
+
+        if let Some(path) = self.find_in_path(&file_name) {
+            return found(path, BinarySource::SystemPath);
+        }
+
+        Err(anyhow::anyhow!(
+            "Binary not found: {} (checked {:?}, binaryPaths in config.json and PATH)",
+            file_name, bundled
+        ))
+    }
+
+    fn find_in_path(&self, file_name: &str) -> Option<PathBuf> {
+        self.path_dirs.iter()
+            .map(|dir| dir.join(file_name))
+            .find(|candidate| is_executable(candidate))
+    }
+
+    /// Resuelve todos los binarios del agente. Devuelve los encontrados y los
+    /// nombres de los que faltan.
+    pub fn resolve_all(&self) -> (Vec<ResolvedBinary>, Vec<String>) {
+        let resolved: Vec<ResolvedBinary> = [MEDIAMTX, FFMPEG, CLOUDFLARED]
+            .into_iter()
+            .filter_map(|name| self.resolve(name).ok())
+            .collect();
+        let missing = missing_binaries(&resolved);
+        (resolved, missing)
+    }
+}
+
+/// Nombres de los binarios del agente que no están en `resolved`
+pub fn missing_binaries(resolved: &[ResolvedBinary]) -> Vec<String> {
+    [MEDIAMTX, FFMPEG, CLOUDFLARED]
+        .into_iter()
+        .filter(|name| !resolved.iter().any(|binary| binary.name == *name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Nombre de archivo del binario en esta plataforma (`ffmpeg.exe` en Windows)
+pub fn binary_file_name(name: &str) -> String {
+    format!("{}{}", name, std::env::consts::EXE_SUFFIX)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Nombre que no está en `src-tauri/bin` ni en el `PATH` real
+    const TOOL: &str = "stream-agent-test-tool";
+
+    fn write_tool(dir: &Path, mode: u32) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join(binary_file_name(TOOL));
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_resolution_order() {
+        let root = std::env::temp_dir().join(format!("stream-agent-binaries-{}", std::process::id()));
+        let resources = root.join("resources");
+        let path_dir = root.join("path");
+        let bundled = write_tool(&resources.join("bin"), 0o755);
+        let override_path = write_tool(&root.join("override"), 0o755);
+        let on_path = write_tool(&path_dir, 0o755);
+
+        let mut resolver = BinaryResolver::new(resources, HashMap::from([(TOOL.to_string(), override_path.clone())]));
+        resolver.path_dirs = vec![path_dir];
+
+        // Lo empaquetado con la app le gana a binaryPaths
+        let binary = resolver.resolve(TOOL).unwrap();
+        assert_eq!((binary.path, binary.source), (bundled.clone(), BinarySource::Bundled));
+
+        // binaryPaths le gana al PATH
+        std::fs::remove_file(&bundled).unwrap();
+        let binary = resolver.resolve(TOOL).unwrap();
+        assert_eq!((binary.path, binary.source), (override_path.clone(), BinarySource::ConfigOverride));
+
+        // Una ruta configurada que no es ejecutable se saltea
+        std::fs::set_permissions(&override_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let binary = resolver.resolve(TOOL).unwrap();
+        assert_eq!((binary.path, binary.source), (on_path.clone(), BinarySource::SystemPath));
+
+        std::fs::remove_file(&on_path).unwrap();
+        assert!(resolver.resolve(TOOL).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_missing_binaries() {
+        let binary = |name: &str| ResolvedBinary {
+            name: name.to_string(),
+            path: PathBuf::from("/usr/bin").join(name),
+            source: BinarySource::SystemPath,
+        };
+        assert_eq!(missing_binaries(&[]), vec![MEDIAMTX, FFMPEG, CLOUDFLARED]);
+        assert_eq!(missing_binaries(&[binary(FFMPEG)]), vec![MEDIAMTX, CLOUDFLARED]);
+        assert!(missing_binaries(&[binary(CLOUDFLARED), binary(MEDIAMTX), binary(FFMPEG)]).is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::supervisor::binaries::{BinaryResolver, CLOUDFLARED, FFMPEG, MEDIAMTX};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Tiempo máximo para cada consulta a un binario
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Encoders H.264 en orden de preferencia. libx264 da la mejor calidad; los
+/// demás son de hardware o alternativas que traen algunos builds.
+const H264_ENCODERS: [&str; 7] = [
+    "libx264",
+    "h264_nvenc",
+    "h264_qsv",
+    "h264_amf",
+    "h264_videotoolbox",
+    "h264_mf",
+    "libopenh264",
+];
+
+/// Lo que soportan las herramientas instaladas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCapabilities {
+    /// `None` si no se encontró FFmpeg o no respondió
+    pub ffmpeg: Option<FfmpegCapabilities>,
+    pub mediamtx_version: Option<String>,
+    pub cloudflared_version: Option<String>,
+    pub probed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegCapabilities {
+    pub version: String,
+    pub video_encoders: Vec<String>,
+    pub audio_encoders: Vec<String>,
+    pub input_protocols: Vec<String>,
+    pub output_protocols: Vec<String>,
+}
+
+/// Configuración que el FFmpeg local no puede cumplir. Reintentar no sirve.
+#[derive(Debug, thiserror::Error)]
+#[error("FFmpeg does not support {0}")]
+pub struct UnsupportedByFfmpeg(pub String);
+
+impl FfmpegCapabilities {
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.video_encoders.iter().chain(&self.audio_encoders).any(|e| e == name)
+    }
+
+    pub fn has_output_protocol(&self, name: &str) -> bool {
+        self.output_protocols.iter().any(|p| p == name)
+    }
+
+    /// Mejor encoder H.264 disponible, o `None` si no hay ninguno
+    pub fn h264_encoder(&self) -> Option<&'static str> {
+        H264_ENCODERS.into_iter().find(|encoder| self.has_encoder(encoder))
+    }
+}
+
+/// Consulta versiones y capacidades de FFmpeg, MediaMTX y cloudflared. Los
+/// binarios que falten o fallen quedan en `None`.
+pub async fn probe_tools(resolver: &BinaryResolver) -> ToolCapabilities {
+    let ffmpeg = match resolver.resolve(FFMPEG) {
+        Ok(binary) => match probe_ffmpeg(&binary.path).await {
+            Ok(capabilities) => Some(capabilities),
+            Err(e) => {
+                log::warn!("Failed to probe FFmpeg: {:#}", e);
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    let mediamtx_version = probe_version(resolver, MEDIAMTX).await;
+    let cloudflared_version = probe_version(resolver, CLOUDFLARED).await;
+
+    match &ffmpeg {
+        Some(ffmpeg) => log::info!(
+            "FFmpeg {} ({} video encoders, H.264: {})",
+            ffmpeg.version,
+            ffmpeg.video_encoders.len(),
+            ffmpeg.h264_encoder().unwrap_or("none")
+        ),
+        None => log::warn!("FFmpeg capabilities unknown"),
+    }
+    log::info!("MediaMTX version: {}", mediamtx_version.as_deref().unwrap_or("unknown"));
+    log::info!("Cloudflared version: {}", cloudflared_version.as_deref().unwrap_or("unknown"));
+
+    ToolCapabilities { ffmpeg, mediamtx_version, cloudflared_version, probed_at: Utc::now() }
+}
+
+async fn probe_ffmpeg(bin_path: &Path) -> Result<FfmpegCapabilities> {
+    let version = parse_version(&run_tool(bin_path, &["-hide_banner", "-version"]).await?)
+        .ok_or_else(|| anyhow::anyhow!("Unrecognized ffmpeg -version output"))?;
+    let (video_encoders, audio_encoders) = parse_encoders(&run_tool(bin_path, &["-hide_banner", "-encoders"]).await?);
+    let (input_protocols, output_protocols) = parse_protocols(&run_tool(bin_path, &["-hide_banner", "-protocols"]).await?);
+
+    Ok(FfmpegCapabilities { version, video_encoders, audio_encoders, input_protocols, output_protocols })
+}
+
+async fn probe_version(resolver: &BinaryResolver, name: &str) -> Option<String> {
+    let binary = resolver.resolve(name).ok()?;
+    match run_tool(&binary.path, &["--version"]).await {
+        Ok(output) => parse_version(&output),
+        Err(e) => {
+            log::warn!("Failed to get {} version: {:#}", name, e);
+            None
+        }
+    }
+}
+
+/// Ejecuta un binario y devuelve su stdout (o stderr si stdout vino vacío)
+async fn run_tool(bin_path: &Path, args: &[&str]) -> Result<String> {
+    let mut cmd = tokio::process::Command::new(bin_path);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let output = tokio::time::timeout(PROBE_TIMEOUT, cmd.output()).await
+        .with_context(|| format!("{:?} {} timed out", bin_path, args.join(" ")))?
+        .with_context(|| format!("Failed to run {:?}", bin_path))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    Ok(stdout.into_owned())
+}
+
+/// Versión de la primera línea: `ffmpeg version 6.1.1 ...`,
+/// `cloudflared version 2024.8.2 (built ...)` o simplemente `v1.9.0`
+pub fn parse_version(output: &str) -> Option<String> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let mut words = line.split_whitespace();
+    let version = if line.contains("version") {
+        words.skip_while(|w| *w != "version").nth(1)
+    } else {
+        words.next()
+    };
+    version.map(str::to_string)
+}
+
+/// Parsea `ffmpeg -encoders` en (video, audio)
+pub fn parse_encoders(output: &str) -> (Vec<String>, Vec<String>) {
+    let mut video = Vec::new();
+    let mut audio = Vec::new();
+
+    // La lista empieza después de la línea " ------" que cierra la leyenda
+    let list = output.lines().skip_while(|l| l.trim() != "------").skip(1);
+    for line in list {
+        let mut fields = line.split_whitespace();
+        let (Some(flags), Some(name)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        match flags.chars().next() {
+            Some('V') => video.push(name.to_string()),
+            Some('A') => audio.push(name.to_string()),
+            _ => {}
+        }
+    }
+
+    (video, audio)
+}
+
+/// Parsea `ffmpeg -protocols` en (entrada, salida)
+pub fn parse_protocols(output: &str) -> (Vec<String>, Vec<String>) {
+    let mut input = Vec::new();
+    let mut output_protocols = Vec::new();
+    let mut section = None;
+
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line {
+            "Input:" => section = Some(&mut input),
+            "Output:" => section = Some(&mut output_protocols),
+            _ => {
+                if let Some(list) = section.as_deref_mut() {
+                    list.push(line.to_string());
+                }
+            }
+        }
+    }
+
+    (input, output_protocols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(
+            parse_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers\nbuilt with gcc 13").as_deref(),
+            Some("6.1.1-3ubuntu5")
+        );
+        assert_eq!(
+            parse_version("cloudflared version 2024.8.2 (built 2024-08-07-1202 UTC)").as_deref(),
+            Some("2024.8.2")
+        );
+        assert_eq!(parse_version("v1.9.0\n").as_deref(), Some("v1.9.0"));
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_parse_encoders_and_protocols() {
+        let encoders = "Encoders:\n V..... = Video\n A..... = Audio\n ------\n V....D libx264              libx264 H.264 / AVC (codec h264)\n V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)\n A....D aac                  AAC (Advanced Audio Coding)\n S..... srt                  SubRip subtitle\n";
+        let (video, audio) = parse_encoders(encoders);
+        assert_eq!(video, vec!["libx264", "h264_nvenc"]);
+        assert_eq!(audio, vec!["aac"]);
+
+        let protocols = "Supported file protocols:\nInput:\n  file\n  tcp\n  rtmp\nOutput:\n  file\n  tcp\n";
+        let (input, output) = parse_protocols(protocols);
+        assert_eq!(input, vec!["file", "tcp", "rtmp"]);
+        assert_eq!(output, vec!["file", "tcp"]);
+
+        // Sin libx264 se usa el siguiente encoder disponible
+        let capabilities = FfmpegCapabilities {
+            video_encoders: vec!["h264_qsv".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(capabilities.h264_encoder(), Some("h264_qsv"));
+    }
+}
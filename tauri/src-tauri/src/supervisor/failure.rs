@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Motivo por el que falló un proceso FFmpeg, deducido de su stderr
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FailureReason {
+    /// La cámara rechazó las credenciales (401)
+    Unauthorized,
+    ConnectionRefused,
+    /// Sin ruta, sin DNS o sin respuesta del host
+    HostUnreachable,
+    /// La ruta del stream no existe en la cámara (404)
+    StreamNotFound,
+    /// FFmpeg no puede decodificar/codificar el stream
+    UnsupportedCodec,
+    /// MediaMTX rechazó la publicación
+    PublishRejected,
+    Unknown,
+}
+
+impl FailureReason {
+    /// `false` si reintentar no va a cambiar nada hasta que alguien corrija la
+    /// configuración (contraseña equivocada, códec no soportado)
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, FailureReason::Unauthorized | FailureReason::UnsupportedCodec)
+    }
+
+    /// Prioridad al combinar varias líneas: gana la causa más específica
+    fn specificity(&self) -> u8 {
+        match self {
+            FailureReason::Unknown => 0,
+            FailureReason::PublishRejected => 1,
+            FailureReason::ConnectionRefused | FailureReason::HostUnreachable => 2,
+            FailureReason::StreamNotFound => 3,
+            FailureReason::Unauthorized | FailureReason::UnsupportedCodec => 4,
+        }
+    }
+}
+
+/// Último fallo de un proceso, para mostrar en la UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureInfo {
+    pub reason: FailureReason,
+    /// Línea de stderr que permitió clasificarlo
+    pub message: Option<String>,
+    pub exit_status: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Clasifica una línea de stderr de FFmpeg. Devuelve `None` si la línea no
+/// indica ningún error conocido.
+pub fn classify_ffmpeg_line(line: &str) -> Option<FailureReason> {
+    let lower = line.to_lowercase();
+
+    // Errores al publicar en MediaMTX (la salida es RTSP con ANNOUNCE/RECORD)
+    if lower.contains("method announce failed") || lower.contains("method record failed") {
+        return Some(FailureReason::PublishRejected);
+    }
+
+    if lower.contains("401 unauthorized") || lower.contains("failed: 401") {
+        return Some(FailureReason::Unauthorized);
+    }
+    if lower.contains("404 not found") || lower.contains("failed: 404") {
+        return Some(FailureReason::StreamNotFound);
+    }
+    if lower.contains("connection refused") {
+        return Some(FailureReason::ConnectionRefused);
+    }
+
+    const UNREACHABLE: [&str; 6] = [
+        "no route to host",
+        "network is unreachable",
+        "host is unreachable",
+        "connection timed out",
+        "name or service not known",
+        "temporary failure in name resolution",
+    ];
+    if UNREACHABLE.iter().any(|pattern| lower.contains(pattern)) {
+        return Some(FailureReason::HostUnreachable);
+    }
+
+    const CODEC: [&str; 6] = [
+        "unsupported codec",
+        "decoder (codec",
+        "unknown encoder",
+        "encoder not found",
+        "codec not currently supported in container",
+        "could not find tag for codec",
+    ];
+    if CODEC.iter().any(|pattern| lower.contains(pattern)) {
+        return Some(FailureReason::UnsupportedCodec);
+    }
+
+    None
+}
+
+/// Acumula lo que se va detectando en el stderr de un proceso
+#[derive(Debug, Default)]
+pub struct FailureDetector {
+    detected: Option<(FailureReason, String)>,
+}
+
+impl FailureDetector {
+    pub fn observe(&mut self, line: &str) {
+        let Some(reason) = classify_ffmpeg_line(line) else {
+            return;
+        };
+        let more_specific = self.detected.as_ref()
+            .map_or(true, |(current, _)| reason.specificity() >= current.specificity());
+        if more_specific {
+            self.detected = Some((reason, line.to_string()));
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.detected = None;
+    }
+
+    /// Resultado final al terminar el proceso; `Unknown` si nada coincidió
+    pub fn conclude(&self, exit_status: Option<String>) -> FailureInfo {
+        let (reason, message) = match &self.detected {
+            Some((reason, line)) => (*reason, Some(line.clone())),
+            None => (FailureReason::Unknown, None),
+        };
+        FailureInfo { reason, message, exit_status, at: Utc::now() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_ffmpeg_errors() {
+        let cases = [
+            ("[rtsp @ 0x55d0c8] method DESCRIBE failed: 401 Unauthorized", FailureReason::Unauthorized),
+            ("[rtsp @ 0x55d0c8] method DESCRIBE failed: 404 Not Found", FailureReason::StreamNotFound),
+            ("[tcp @ 0x55d0c8] Connection to tcp://192.168.1.240:554?timeout=0 failed: Connection refused", FailureReason::ConnectionRefused),
+            ("[tcp @ 0x55d0c8] Connection to tcp://192.168.1.240:554?timeout=0 failed: No route to host", FailureReason::HostUnreachable),
+            ("[rtsp @ 0x55d0c8] method ANNOUNCE failed: 400 Bad Request", FailureReason::PublishRejected),
+            ("[rtsp @ 0x55d0c8] Could not find tag for codec pcm_alaw in stream #1, codec not currently supported in container", FailureReason::UnsupportedCodec),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(classify_ffmpeg_line(line), Some(expected), "{}", line);
+        }
+        assert_eq!(classify_ffmpeg_line("Input #0, rtsp, from 'rtsp://192.168.1.240:554/stream':"), None);
+    }
+
+    #[test]
+    fn test_detector_keeps_most_specific_reason() {
+        let mut detector = FailureDetector::default();
+        detector.observe("[rtsp @ 0x55d0c8] method DESCRIBE failed: 401 Unauthorized");
+        detector.observe("rtsp://192.168.1.240:554/stream: Connection refused");
+
+        let failure = detector.conclude(Some("exit status: 1".to_string()));
+        assert_eq!(failure.reason, FailureReason::Unauthorized);
+        assert!(!failure.reason.is_retryable());
+
+        detector.reset();
+        assert_eq!(detector.conclude(None).reason, FailureReason::Unknown);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::supervisor::process_manager::send_terminate;
+use anyhow::Result;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+/// Qué proceso lanzar
+#[derive(Debug, Clone)]
+pub struct LaunchSpec {
+    /// Nombre del handle (`mediamtx`, `ffmpeg-cam1`, ...)
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<OsString>,
+    /// Abrir stdin para poder pedir un cierre ordenado (ej. `q` en FFmpeg)
+    pub stdin: bool,
+}
+
+impl LaunchSpec {
+    pub fn new(name: impl Into<String>, program: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), program: program.into(), args: Vec::new(), stdin: false }
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_stdin(mut self) -> Self {
+        self.stdin = true;
+        self
+    }
+}
+
+/// Lanza procesos hijos. El supervisor solo habla con esta interfaz, así los
+/// tests pueden reemplazar los binarios reales por procesos simulados.
+pub trait ProcessLauncher: Send + Sync {
+    fn spawn(&self, spec: &LaunchSpec) -> std::io::Result<Box<dyn ManagedChild>>;
+}
+
+/// Operaciones sobre un proceso lanzado
+pub trait ManagedChild: Send + std::fmt::Debug {
+    fn id(&self) -> u32;
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>>;
+    fn wait(&mut self) -> std::io::Result<ExitStatus>;
+    fn kill(&mut self) -> std::io::Result<()>;
+    /// Pide que termine con SIGTERM; en Windows no hay equivalente (ver
+    /// `CAN_TERMINATE`)
+    fn terminate(&mut self) -> Result<()>;
+    fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>>;
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>>;
+}
+
+/// Lanza los binarios reales con `std::process::Command`
+#[derive(Debug, Default)]
+pub struct SystemLauncher;
+
+impl ProcessLauncher for SystemLauncher {
+    fn spawn(&self, spec: &LaunchSpec) -> std::io::Result<Box<dyn ManagedChild>> {
+        let mut cmd = Command::new(&spec.program);
+        cmd.args(&spec.args)
+            .stdin(if spec.stdin { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        Ok(Box::new(cmd.spawn()?))
+    }
+}
+
+impl ManagedChild for Child {
+    fn id(&self) -> u32 {
+        Child::id(self)
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        Child::try_wait(self)
+    }
+
+    fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        Child::wait(self)
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        Child::kill(self)
+    }
+
+    fn terminate(&mut self) -> Result<()> {
+        send_terminate(Child::id(self))
+    }
+
+    fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>> {
+        self.stdin.take().map(|s| Box::new(s) as Box<dyn Write + Send>)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>)
+    }
+}
+
+/// Procesos simulados para tests: cada lanzamiento sigue el guion que se le
+/// haya dado al nombre del proceso.
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// PIDs simulados, lejos de los reales
+    static NEXT_PID: AtomicU32 = AtomicU32::new(4_000_000);
+
+    /// Cómo se comporta un proceso simulado
+    #[derive(Debug, Clone, Default)]
+    pub struct FakeScript {
+        /// Termina solo después de este tiempo; `None` corre hasta que lo detengan
+        pub exit_after: Option<Duration>,
+        pub exit_code: i32,
+        /// Líneas que escribe por stderr al arrancar
+        pub stderr: Vec<String>,
+        /// El spawn falla (binario inexistente, sin permisos)
+        pub refuse_spawn: bool,
+    }
+
+    impl FakeScript {
+        pub fn exits_after(ms: u64, exit_code: i32) -> Self {
+            Self { exit_after: Some(Duration::from_millis(ms)), exit_code, ..Self::default() }
+        }
+
+        pub fn refuses_spawn() -> Self {
+            Self { refuse_spawn: true, ..Self::default() }
+        }
+
+        pub fn with_stderr(mut self, line: &str) -> Self {
+            self.stderr.push(line.to_string());
+            self
+        }
+    }
+
+    /// Launcher con guiones por nombre de proceso. Los guiones se consumen en
+    /// orden; cuando se acaban el proceso corre hasta que lo detengan.
+    #[derive(Debug, Default)]
+    pub struct FakeLauncher {
+        scripts: Mutex<HashMap<String, VecDeque<FakeScript>>>,
+        spawned: Mutex<Vec<LaunchSpec>>,
+    }
+
+    impl FakeLauncher {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Agrega el guion del próximo lanzamiento de `name`
+        pub fn script(&self, name: &str, script: FakeScript) -> &Self {
+            self.scripts.lock().unwrap()
+                .entry(name.to_string())
+                .or_default()
+                .push_back(script);
+            self
+        }
+
+        /// Cuántas veces se lanzó (con éxito) un proceso
+        pub fn spawn_count(&self, name: &str) -> usize {
+            self.spawned.lock().unwrap().iter().filter(|spec| spec.name == name).count()
+        }
+
+        pub fn last_spec(&self, name: &str) -> Option<LaunchSpec> {
+            self.spawned.lock().unwrap().iter().rev().find(|spec| spec.name == name).cloned()
+        }
+    }
+
+    impl ProcessLauncher for FakeLauncher {
+        fn spawn(&self, spec: &LaunchSpec) -> std::io::Result<Box<dyn ManagedChild>> {
+            let script = self.scripts.lock().unwrap()
+                .get_mut(&spec.name)
+                .and_then(|queue| queue.pop_front())
+                .unwrap_or_default();
+
+            if script.refuse_spawn {
+                return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "fake spawn refused"));
+            }
+
+            self.spawned.lock().unwrap().push(spec.clone());
+            let stderr = script.stderr.iter().map(|l| format!("{}\n", l)).collect::<String>();
+            Ok(Box::new(FakeChild {
+                pid: NEXT_PID.fetch_add(1, Ordering::SeqCst),
+                started: Instant::now(),
+                script,
+                quit: Arc::new(AtomicBool::new(false)),
+                stopped_with: None,
+                stdin_open: spec.stdin,
+                stderr: Some(stderr.into_bytes()),
+            }))
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct FakeChild {
+        pid: u32,
+        started: Instant,
+        script: FakeScript,
+        /// Se activa al recibir algo por stdin
+        quit: Arc<AtomicBool>,
+        /// Código con el que terminó por terminate/kill
+        stopped_with: Option<ExitStatus>,
+        stdin_open: bool,
+        stderr: Option<Vec<u8>>,
+    }
+
+    impl ManagedChild for FakeChild {
+        fn id(&self) -> u32 {
+            self.pid
+        }
+
+        fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+            if let Some(status) = self.stopped_with {
+                return Ok(Some(status));
+            }
+            if self.quit.load(Ordering::SeqCst) {
+                return Ok(Some(exit_status(0)));
+            }
+            match self.script.exit_after {
+                Some(after) if self.started.elapsed() >= after => Ok(Some(exit_status(self.script.exit_code))),
+                _ => Ok(None),
+            }
+        }
+
+        fn wait(&mut self) -> std::io::Result<ExitStatus> {
+            loop {
+                if let Some(status) = self.try_wait()? {
+                    return Ok(status);
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        fn kill(&mut self) -> std::io::Result<()> {
+            self.stopped_with.get_or_insert(exit_status(137));
+            Ok(())
+        }
+
+        fn terminate(&mut self) -> Result<()> {
+            self.stopped_with.get_or_insert(exit_status(143));
+            Ok(())
+        }
+
+        fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>> {
+            if !std::mem::take(&mut self.stdin_open) {
+                return None;
+            }
+            Some(Box::new(QuitWriter(self.quit.clone())))
+        }
+
+        fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+            None
+        }
+
+        fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+            self.stderr.take().map(|bytes| Box::new(Cursor::new(bytes)) as Box<dyn Read + Send>)
+        }
+    }
+
+    /// stdin simulado: cualquier escritura cuenta como pedido de cierre
+    struct QuitWriter(Arc<AtomicBool>);
+
+    impl Write for QuitWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if !buf.is_empty() {
+                self.0.store(true, Ordering::SeqCst);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    fn exit_status(code: i32) -> ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[cfg(windows)]
+    fn exit_status(code: i32) -> ExitStatus {
+        use std::os::windows::process::ExitStatusExt;
+        ExitStatus::from_raw(code as u32)
+    }
+}
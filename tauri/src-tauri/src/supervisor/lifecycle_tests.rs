@@ -0,0 +1,232 @@
+// Escenarios del agente completo (inicio, caídas, watchdog y cierre) con los
+// procesos simulados por `FakeLauncher`
+
+use crate::agent::*;
+use crate::app_state::*;
+use crate::cameras::*;
+use crate::supervisor::fake::{FakeLauncher, FakeScript};
+use crate::supervisor::*;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Agente con procesos simulados. El listener hace de puerto RTSP de
+/// MediaMTX para que pase el chequeo de readiness.
+struct TestAgent {
+    state: AppState,
+    launcher: Arc<FakeLauncher>,
+    launch: AgentLaunch,
+    dir: PathBuf,
+    _rtsp: TcpListener,
+}
+
+impl TestAgent {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("stream-agent-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let rtsp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mediamtx_config = dir.join("mediamtx.yml");
+        std::fs::write(
+            &mediamtx_config,
+            format!("rtspAddress: {}\napi: no\n", rtsp.local_addr().unwrap()),
+        ).unwrap();
+
+        let cameras_config = dir.join("cameras.json");
+        std::fs::write(
+            &cameras_config,
+            r#"{"cameras":[{"id":"cam1","name":"Cancha 1","rtspUrl":"rtsp://192.0.2.10:554/stream","enabled":true}]}"#,
+        ).unwrap();
+
+        let mut config = AgentConfig::default();
+        config.reconnect_policy = ReconnectPolicy {
+            retry_delay_ms: 50,
+            max_delay_ms: 200,
+            stall_timeout_ms: 0,
+            jitter: JitterMode::None,
+            ..ReconnectPolicy::default()
+        };
+        config.readiness_policy = ReadinessPolicy { timeout_ms: 2000, interval_ms: 20 };
+        config.shutdown_policy = ShutdownPolicy { quit_timeout_ms: 500, terminate_grace_ms: 500 };
+
+        let launcher = Arc::new(FakeLauncher::new());
+        let state = AppState::with_launcher(config, launcher.clone());
+        let launch = AgentLaunch {
+            mediamtx_bin: dir.join("mediamtx"),
+            mediamtx_config,
+            ffmpeg_bin: dir.join("ffmpeg"),
+            cameras_config,
+            cloudflared: None,
+            ffmpeg_capabilities: None,
+            watchdog_interval: Duration::from_millis(50),
+            auto_start_cameras: true,
+        };
+
+        Self { state, launcher, launch, dir, _rtsp: rtsp }
+    }
+
+    async fn start(&self) {
+        start_agent(&self.state, self.launch.clone(), None).await.unwrap();
+    }
+
+    async fn camera(&self) -> CameraSnapshot {
+        get_camera(&self.state.cameras, "cam1").await.unwrap().snapshot()
+    }
+}
+
+impl Drop for TestAgent {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Espera hasta 5s a que se cumpla `condition`
+async fn eventually<F, Fut>(what: &str, mut condition: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !condition().await {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_start_and_stop_agent() {
+    let agent = TestAgent::new("start-stop");
+    agent.start().await;
+
+    assert_eq!(agent.state.lifecycle.lock().await.state, AgentState::Running);
+    assert_eq!(agent.state.mediamtx.lock().await.status, ProcessStatus::Running);
+    assert_eq!(agent.camera().await.status, ProcessStatus::Running);
+    assert_eq!(agent.launcher.spawn_count("mediamtx"), 1);
+    let ffmpeg = agent.launcher.last_spec("ffmpeg-cam1").unwrap();
+    assert!(ffmpeg.stdin);
+    assert!(ffmpeg.args.iter().any(|arg| arg == "rtsp://192.0.2.10:554/stream"));
+
+    // Un segundo inicio se rechaza
+    assert!(start_agent(&agent.state, agent.launch.clone(), None).await.is_err());
+
+    shutdown_agent(&agent.state, None, "test").await.unwrap();
+    assert_eq!(agent.state.lifecycle.lock().await.state, AgentState::Stopped);
+    assert!(agent.state.watchdog.lock().await.is_none());
+    let mediamtx = agent.state.mediamtx.lock().await;
+    assert_eq!(mediamtx.status, ProcessStatus::Stopped);
+    assert!(mediamtx.process.is_none());
+    drop(mediamtx);
+    assert_eq!(agent.camera().await.status, ProcessStatus::Stopped);
+    assert!(shutdown_agent(&agent.state, None, "test").await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_start_fails_when_mediamtx_cannot_spawn() {
+    let agent = TestAgent::new("mediamtx-missing");
+    agent.launcher.script("mediamtx", FakeScript::refuses_spawn());
+
+    assert!(start_agent(&agent.state, agent.launch.clone(), None).await.is_err());
+    let lifecycle = agent.state.lifecycle.lock().await.clone();
+    assert_eq!(lifecycle.state, AgentState::Stopped);
+    assert!(lifecycle.reason.starts_with("Startup failed"), "{}", lifecycle.reason);
+    assert_eq!(agent.launcher.spawn_count("ffmpeg-cam1"), 0);
+
+    // Sin restos del intento fallido, el próximo inicio funciona
+    agent.start().await;
+    assert_eq!(agent.state.lifecycle.lock().await.state, AgentState::Running);
+    shutdown_agent(&agent.state, None, "test").await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shutdown_waits_for_start_to_finish() {
+    let agent = TestAgent::new("shutdown-starting");
+    transition_agent(&agent.state, None, AgentState::Starting, "test").await.unwrap();
+
+    let finish_start = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(agent.state.lifecycle.lock().await.state, AgentState::Starting);
+        transition_agent(&agent.state, None, AgentState::Running, "test").await.unwrap();
+    };
+    let (result, ()) = tokio::join!(shutdown_when_settled(&agent.state, None, "test"), finish_start);
+    result.unwrap();
+    assert_eq!(agent.state.lifecycle.lock().await.state, AgentState::Stopped);
+
+    // Ya detenido no hay nada que hacer
+    shutdown_when_settled(&agent.state, None, "test").await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cameras_not_started_without_auto_start() {
+    let mut agent = TestAgent::new("no-auto-start");
+    agent.launch.auto_start_cameras = false;
+    agent.start().await;
+
+    // Cargada pero detenida, y el agente no se considera degradado
+    assert_eq!(agent.camera().await.status, ProcessStatus::Stopped);
+    assert_eq!(agent.launcher.spawn_count("ffmpeg-cam1"), 0);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(agent.state.lifecycle.lock().await.state, AgentState::Running);
+    assert_eq!(agent.launcher.spawn_count("ffmpeg-cam1"), 0);
+    shutdown_agent(&agent.state, None, "test").await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watchdog_restarts_crashed_camera() {
+    let agent = TestAgent::new("camera-crash");
+    agent.launcher.script(
+        "ffmpeg-cam1",
+        FakeScript::exits_after(100, 1)
+            .with_stderr("[tcp @ 0x55d0c8] Connection to tcp://192.0.2.10:554?timeout=0 failed: Connection refused"),
+    );
+    agent.start().await;
+
+    eventually("the camera restart", || async { agent.launcher.spawn_count("ffmpeg-cam1") == 2 }).await;
+    eventually("the camera to run again", || async { agent.camera().await.status == ProcessStatus::Running }).await;
+
+    let camera = agent.camera().await;
+    assert_eq!(camera.stats.restarts, 1);
+    let failure = camera.last_failure.expect("crash should be classified");
+    assert_eq!(failure.reason, FailureReason::ConnectionRefused);
+
+    shutdown_agent(&agent.state, None, "test").await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_camera_not_restarted_after_auth_failure() {
+    let agent = TestAgent::new("camera-auth");
+    agent.launcher.script(
+        "ffmpeg-cam1",
+        FakeScript::exits_after(100, 1).with_stderr("[rtsp @ 0x55d0c8] method DESCRIBE failed: 401 Unauthorized"),
+    );
+    agent.start().await;
+
+    eventually("the camera to fail", || async { agent.camera().await.status == ProcessStatus::Failed }).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(agent.launcher.spawn_count("ffmpeg-cam1"), 1);
+
+    let lifecycle = agent.state.lifecycle.lock().await.clone();
+    assert_eq!(lifecycle.state, AgentState::Degraded);
+    assert!(lifecycle.reason.contains("cam1 (failed)"), "{}", lifecycle.reason);
+
+    shutdown_agent(&agent.state, None, "test").await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watchdog_restarts_mediamtx_and_cameras() {
+    let agent = TestAgent::new("mediamtx-crash");
+    agent.launcher.script("mediamtx", FakeScript::exits_after(300, 1));
+    agent.start().await;
+    assert_eq!(agent.launcher.spawn_count("ffmpeg-cam1"), 1);
+
+    eventually("the MediaMTX restart", || async { agent.launcher.spawn_count("mediamtx") == 2 }).await;
+    eventually("the cameras to be relaunched", || async { agent.launcher.spawn_count("ffmpeg-cam1") == 2 }).await;
+
+    let mediamtx = agent.state.mediamtx.lock().await;
+    assert_eq!(mediamtx.status, ProcessStatus::Running);
+    assert_eq!(mediamtx.stats.restarts, 1);
+    drop(mediamtx);
+    assert_eq!(agent.camera().await.status, ProcessStatus::Running);
+
+    shutdown_agent(&agent.state, None, "test").await.unwrap();
+}
@@ -1,5 +1,30 @@
+pub mod binaries;
+pub mod capabilities;
+pub mod failure;
+pub mod launcher;
+pub mod output;
+pub mod pidfile;
 pub mod process_manager;
+pub mod progress;
+pub mod readiness;
+pub mod resources;
+pub mod tunnel;
 pub mod reconnect;
+pub mod watchdog;
 
+#[cfg(test)]
+mod lifecycle_tests;
+
+pub use binaries::*;
+pub use capabilities::*;
+pub use failure::*;
+pub use launcher::*;
+pub use output::*;
+pub use pidfile::*;
 pub use process_manager::*;
+pub use progress::*;
+pub use readiness::*;
+pub use resources::*;
+pub use tunnel::*;
 pub use reconnect::*;
+pub use watchdog::*;
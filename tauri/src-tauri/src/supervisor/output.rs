@@ -0,0 +1,133 @@
+use crate::app_state::{LogBuffer, ProcessHandle};
+use chrono::Utc;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::Mutex;
+
+/// Handler opcional que ve cada línea antes de guardarla. Si devuelve `true`
+/// la línea se considera consumida y no pasa al `LogBuffer`.
+pub type LineHandler = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Destino de las líneas que escriben los procesos hijos por stdout/stderr
+#[derive(Clone)]
+pub struct LogSink {
+    logs: Arc<Mutex<LogBuffer>>,
+    app_handle: Option<tauri::AppHandle>,
+    line_handler: Option<LineHandler>,
+}
+
+/// Payload del evento `log-line`
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLinePayload {
+    pub component: String,
+    pub line: String,
+}
+
+impl LogSink {
+    pub fn new(logs: Arc<Mutex<LogBuffer>>, app_handle: Option<tauri::AppHandle>) -> Self {
+        Self { logs, app_handle, line_handler: None }
+    }
+
+    /// Devuelve una copia del sink que pasa cada línea por `handler` primero
+    pub fn with_line_handler(&self, handler: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            logs: self.logs.clone(),
+            app_handle: self.app_handle.clone(),
+            line_handler: Some(Arc::new(handler)),
+        }
+    }
+
+    /// Agrega una línea con timestamp al buffer del componente y la emite a la UI.
+    ///
+    /// Se llama desde los hilos lectores (fuera del runtime de tokio), por eso
+    /// usa `blocking_lock`.
+    pub fn push(&self, component: &str, line: &str) {
+        if let Some(handler) = &self.line_handler {
+            if handler(line) {
+                return;
+            }
+        }
+
+        let line = format!("{} {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), line);
+
+        self.logs.blocking_lock().append(component.to_string(), line.clone());
+
+        if let Some(app_handle) = &self.app_handle {
+            let payload = LogLinePayload { component: component.to_string(), line };
+            let _ = app_handle.emit("log-line", payload);
+        }
+    }
+}
+
+/// Lanza hilos que drenan stdout y stderr del proceso hacia el `LogSink`,
+/// usando el nombre del handle como componente (`mediamtx`, `ffmpeg-cam1`, ...).
+///
+/// Sin lectores el pipe se llena y el proceso hijo queda bloqueado al escribir.
+pub fn attach_output_readers(handle: &mut ProcessHandle, sink: &LogSink) {
+    let Some(child) = handle.child.as_mut() else {
+        return;
+    };
+
+    if let Some(stdout) = child.take_stdout() {
+        spawn_reader(format!("{}-stdout", handle.name), handle.name.clone(), stdout, sink.clone());
+    }
+    if let Some(stderr) = child.take_stderr() {
+        spawn_reader(format!("{}-stderr", handle.name), handle.name.clone(), stderr, sink.clone());
+    }
+}
+
+fn spawn_reader<R: Read + Send + 'static>(thread_name: String, component: String, reader: R, sink: LogSink) {
+    let result = std::thread::Builder::new()
+        .name(thread_name)
+        .spawn(move || {
+            if let Err(e) = read_lines(reader, |line| sink.push(&component, line)) {
+                log::debug!("Output reader for {} ended with error: {}", component, e);
+            }
+        });
+
+    if let Err(e) = result {
+        log::error!("Failed to spawn output reader thread: {}", e);
+    }
+}
+
+/// Lee líneas terminadas en `\n` o `\r` hasta EOF.
+///
+/// FFmpeg separa sus líneas de progreso con `\r`, así que no alcanza con `lines()`.
+pub fn read_lines<R: Read>(reader: R, mut on_line: impl FnMut(&str)) -> std::io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        let len = buf.len();
+        for &byte in buf {
+            if byte == b'\n' || byte == b'\r' {
+                flush_line(&mut line, &mut on_line);
+            } else {
+                line.push(byte);
+            }
+        }
+        reader.consume(len);
+    }
+
+    flush_line(&mut line, &mut on_line);
+    Ok(())
+}
+
+fn flush_line(line: &mut Vec<u8>, on_line: &mut impl FnMut(&str)) {
+    if line.is_empty() {
+        return;
+    }
+    let text = String::from_utf8_lossy(line);
+    let text = text.trim_end();
+    if !text.is_empty() {
+        on_line(text);
+    }
+    line.clear();
+}
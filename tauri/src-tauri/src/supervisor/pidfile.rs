@@ -0,0 +1,362 @@
+use crate::app_state::{ProcessHandle, ShutdownPolicy};
+use crate::supervisor::process_manager::{send_kill, send_terminate, CAN_TERMINATE};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Diferencia máxima entre el inicio registrado y el real del proceso para
+/// considerarlo el mismo (el PID pudo haber sido reutilizado por el sistema)
+const START_TIME_TOLERANCE_SECS: i64 = 5;
+
+/// Directorio de PID files, fijado una vez al iniciar la app
+static PID_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Contenido de un PID file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PidRecord {
+    pub name: String,
+    pub pid: u32,
+    pub bin_path: PathBuf,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Lo que el sistema operativo informa sobre un proceso vivo
+#[derive(Debug, Clone)]
+pub struct ProcessIdentity {
+    pub exe: PathBuf,
+    /// `None` si la plataforma no permite obtenerlo
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Fija el directorio donde se guardan los PID files (`<config>/pids`)
+pub fn init_pid_dir(config_dir: &Path) -> Result<()> {
+    let dir = config_dir.join("pids");
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create PID directory {:?}", dir))?;
+    let _ = PID_DIR.set(dir);
+    Ok(())
+}
+
+fn pid_file_path(name: &str) -> Option<PathBuf> {
+    PID_DIR.get().map(|dir| dir.join(format!("{}.json", name)))
+}
+
+/// Registra un proceso recién lanzado para poder limpiarlo si la app muere
+pub fn write_pid_file(handle: &ProcessHandle, bin_path: &Path) {
+    let (Some(path), Some(pid)) = (pid_file_path(&handle.name), handle.pid) else {
+        return;
+    };
+
+    let record = PidRecord {
+        name: handle.name.clone(),
+        pid,
+        bin_path: bin_path.canonicalize().unwrap_or_else(|_| bin_path.to_path_buf()),
+        started_at: DateTime::<Utc>::from(handle.started_at),
+    };
+
+    let result = serde_json::to_string_pretty(&record)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| std::fs::write(&path, content).map_err(anyhow::Error::from));
+    if let Err(e) = result {
+        log::warn!("Failed to write PID file {:?}: {}", path, e);
+    }
+}
+
+/// Borra el PID file de un proceso que ya terminó
+pub fn remove_pid_file(name: &str) {
+    let Some(path) = pid_file_path(name) else {
+        return;
+    };
+    if let Err(e) = std::fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("Failed to remove PID file {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Termina los procesos que quedaron de una ejecución anterior (crash o salida
+/// sin `stop_agent`), usando los PID files. Solo se tocan procesos cuyo
+/// ejecutable y hora de inicio coinciden con lo registrado.
+///
+/// Debe llamarse antes de lanzar cualquier proceso. Bloquea hasta
+/// `terminate_grace_ms` (una sola espera para todos los huérfanos), así que
+/// se corre fuera del hilo principal. Devuelve cuántos terminó.
+pub fn cleanup_orphans(policy: &ShutdownPolicy) -> usize {
+    let Some(dir) = PID_DIR.get() else {
+        return 0;
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Failed to read PID directory {:?}: {}", dir, e);
+            return 0;
+        }
+    };
+
+    let mut orphans = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let record: PidRecord = match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| serde_json::from_str(&content).map_err(anyhow::Error::from))
+        {
+            Ok(record) => record,
+            Err(e) => {
+                log::warn!("Ignoring unreadable PID file {:?}: {}", path, e);
+                let _ = std::fs::remove_file(&path);
+                continue;
+            }
+        };
+
+        match process_identity(record.pid) {
+            Some(identity) if is_same_process(&record, &identity) => {
+                log::warn!(
+                    "Found orphaned {} (PID {}) from a previous run, terminating",
+                    record.name, record.pid
+                );
+                if CAN_TERMINATE {
+                    if let Err(e) = send_terminate(record.pid) {
+                        log::warn!("Failed to send terminate signal to PID {}: {}", record.pid, e);
+                    }
+                }
+                // El PID file se borra cuando el proceso termina
+                orphans.push((record, path));
+                continue;
+            }
+            Some(_) => {
+                log::info!("PID {} from {:?} now belongs to another process, leaving it alone", record.pid, path);
+            }
+            None => {
+                log::debug!("{} (PID {}) is no longer running", record.name, record.pid);
+            }
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    let terminated = wait_for_orphans(orphans, policy);
+    if terminated > 0 {
+        log::info!("Terminated {} orphaned processes", terminated);
+    }
+    terminated
+}
+
+/// Un proceso vivo es el registrado si coincide el ejecutable y, cuando se
+/// conoce, la hora de inicio
+fn is_same_process(record: &PidRecord, identity: &ProcessIdentity) -> bool {
+    if !same_executable(&record.bin_path, &identity.exe) {
+        return false;
+    }
+    match identity.started_at {
+        Some(started_at) => (started_at - record.started_at).num_seconds().abs() <= START_TIME_TOLERANCE_SECS,
+        None => true,
+    }
+}
+
+fn same_executable(a: &Path, b: &Path) -> bool {
+    let a = a.canonicalize().unwrap_or_else(|_| a.to_path_buf());
+    let b = b.canonicalize().unwrap_or_else(|_| b.to_path_buf());
+    if cfg!(windows) {
+        let normalize = |p: &Path| {
+            p.to_string_lossy().trim_start_matches(r"\\?\").to_lowercase()
+        };
+        normalize(&a) == normalize(&b)
+    } else {
+        a == b
+    }
+}
+
+/// Espera hasta `terminate_grace_ms` a que terminen los huérfanos que ya
+/// recibieron SIGTERM y fuerza con SIGKILL los que sigan vivos (en Windows
+/// directamente). Devuelve cuántos terminaron; los que no se pudieron matar
+/// conservan su PID file.
+fn wait_for_orphans(orphans: Vec<(PidRecord, PathBuf)>, policy: &ShutdownPolicy) -> usize {
+    let grace_ms = if CAN_TERMINATE { policy.terminate_grace_ms } else { 0 };
+    let deadline = Instant::now() + Duration::from_millis(grace_ms);
+    let mut remaining = orphans;
+    let mut terminated = 0;
+    loop {
+        remaining.retain(|(record, path)| {
+            if process_identity(record.pid).is_some() {
+                return true;
+            }
+            terminated += 1;
+            let _ = std::fs::remove_file(path);
+            false
+        });
+        if remaining.is_empty() || Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    for (record, path) in remaining {
+        log::warn!("PID {} still running after terminate signal, killing", record.pid);
+        match send_kill(record.pid) {
+            Ok(()) => {
+                terminated += 1;
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(e) => log::error!("Failed to terminate orphaned {} (PID {}): {}", record.name, record.pid, e),
+        }
+    }
+    terminated
+}
+
+/// Consulta al sistema el ejecutable y la hora de inicio de un PID
+#[cfg(target_os = "linux")]
+pub fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+    let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+    // Si el binario fue reemplazado, el kernel agrega " (deleted)"
+    let exe = PathBuf::from(exe.to_string_lossy().trim_end_matches(" (deleted)"));
+
+    Some(ProcessIdentity { exe, started_at: linux_start_time(pid) })
+}
+
+#[cfg(target_os = "linux")]
+fn linux_start_time(pid: u32) -> Option<DateTime<Utc>> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // El nombre del proceso va entre paréntesis y puede contener espacios
+    let after_name = &stat[stat.rfind(')')? + 1..];
+    // starttime es el campo 22; después del nombre empieza el campo 3
+    let start_ticks: u64 = after_name.split_whitespace().nth(19)?.parse().ok()?;
+
+    let boot_time: i64 = std::fs::read_to_string("/proc/stat").ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+
+    // SAFETY: sysconf solo lee una constante del sistema
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return None;
+    }
+
+    let millis = boot_time * 1000 + (start_ticks as i64 * 1000) / ticks_per_sec as i64;
+    DateTime::from_timestamp_millis(millis)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "comm="])
+        .output()
+        .ok()?;
+    let exe = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || exe.is_empty() {
+        return None;
+    }
+    Some(ProcessIdentity { exe: PathBuf::from(exe), started_at: None })
+}
+
+#[cfg(windows)]
+pub fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+    let script = format!(
+        "$p = Get-Process -Id {} -ErrorAction Stop; $p.Path; $p.StartTime.ToUniversalTime().ToString('o')",
+        pid
+    );
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim).filter(|l| !l.is_empty());
+    let exe = PathBuf::from(lines.next()?);
+    let started_at = lines.next()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|t| t.with_timezone(&Utc));
+    Some(ProcessIdentity { exe, started_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_same_process() {
+        let started_at = Utc::now();
+        let record = PidRecord {
+            name: "mediamtx".to_string(),
+            pid: 4321,
+            bin_path: PathBuf::from("/opt/stream-agent/bin/mediamtx"),
+            started_at,
+        };
+
+        let ours = ProcessIdentity {
+            exe: PathBuf::from("/opt/stream-agent/bin/mediamtx"),
+            started_at: Some(started_at + chrono::Duration::seconds(1)),
+        };
+        assert!(is_same_process(&record, &ours));
+
+        // PID reutilizado por otro programa
+        let other_exe = ProcessIdentity { exe: PathBuf::from("/usr/bin/bash"), ..ours.clone() };
+        assert!(!is_same_process(&record, &other_exe));
+
+        // Mismo binario, pero lanzado mucho después (otra instancia)
+        let later = ProcessIdentity {
+            started_at: Some(started_at + chrono::Duration::hours(2)),
+            ..ours
+        };
+        assert!(!is_same_process(&record, &later));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_orphans_share_one_grace_period() {
+        let dir = std::env::temp_dir().join(format!("stream-agent-orphans-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Procesos que ignoran SIGTERM: hay que esperar la gracia y matarlos
+        let mut children = Vec::new();
+        let mut orphans = Vec::new();
+        for n in 0..3 {
+            let child = std::process::Command::new("sh")
+                .args(["-c", "trap '' TERM; sleep 5"])
+                .spawn()
+                .unwrap();
+            let path = dir.join(format!("orphan-{}.json", n));
+            std::fs::write(&path, "{}").unwrap();
+            orphans.push((
+                PidRecord {
+                    name: format!("orphan-{}", n),
+                    pid: child.id(),
+                    bin_path: PathBuf::from("/bin/sh"),
+                    started_at: Utc::now(),
+                },
+                path,
+            ));
+            children.push(child);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+        for (record, _) in &orphans {
+            send_terminate(record.pid).unwrap();
+        }
+
+        let policy = ShutdownPolicy { quit_timeout_ms: 0, terminate_grace_ms: 500 };
+        let started = Instant::now();
+        assert_eq!(wait_for_orphans(orphans, &policy), 3);
+        // Una sola espera para los tres, no una por proceso
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(500) && elapsed < Duration::from_millis(1200), "{:?}", elapsed);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        for mut child in children {
+            let _ = child.wait();
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,91 +1,166 @@
-use crate::app_state::{ProcessHandle, ProcessStatus, ReconnectPolicy, ReconnectStats};
+use crate::app_state::{ProcessHandle, ReadinessPolicy, ShutdownPolicy};
+use crate::supervisor::output::{attach_output_readers, LogSink};
+use crate::supervisor::pidfile::{remove_pid_file, write_pid_file};
+use crate::supervisor::launcher::{LaunchSpec, ManagedChild, ProcessLauncher};
+use crate::supervisor::{mediamtx_probes, wait_until_ready, FfmpegCapabilities, UnsupportedByFfmpeg};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+#[cfg(windows)]
 use std::process::{Command, Stdio};
 use std::time::Duration;
 use tauri::Manager;
 use tokio::time::sleep;
 
+/// Lanza un proceso, registra su PID file y conecta su salida al sink
+fn spawn_process(launcher: &dyn ProcessLauncher, spec: &LaunchSpec, sink: &LogSink) -> std::io::Result<ProcessHandle> {
+    let child = launcher.spawn(spec)?;
+    let mut handle = ProcessHandle::new(spec.name.clone(), child);
+    write_pid_file(&handle, &spec.program);
+    attach_output_readers(&mut handle, sink);
+    Ok(handle)
+}
+
 /// Inicia MediaMTX
-pub fn start_mediamtx(bin_path: &Path, config_path: &Path) -> Result<ProcessHandle> {
+pub fn start_mediamtx(
+    launcher: &dyn ProcessLauncher,
+    bin_path: &Path,
+    config_path: &Path,
+    sink: &LogSink,
+) -> Result<ProcessHandle> {
     log::info!("Starting MediaMTX from: {:?}", bin_path);
     
-    let mut cmd = Command::new(bin_path);
-    cmd.arg(config_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
-
-    let child = cmd.spawn()
+    let spec = LaunchSpec::new("mediamtx", bin_path).arg(config_path);
+    let handle = spawn_process(launcher, &spec, sink)
         .context("Failed to spawn MediaMTX process")?;
 
-    log::info!("MediaMTX started with PID: {:?}", child.id());
-    Ok(ProcessHandle::new("mediamtx".to_string(), child))
+    log::info!("MediaMTX started with PID: {:?}", handle.pid);
+    Ok(handle)
+}
+
+/// Inicia MediaMTX y espera a que sus probes de readiness respondan.
+/// Si no queda listo a tiempo, el proceso se mata y se devuelve el error.
+pub async fn launch_mediamtx(
+    launcher: &dyn ProcessLauncher,
+    bin_path: &Path,
+    config_path: &Path,
+    sink: &LogSink,
+    readiness: &ReadinessPolicy,
+) -> Result<ProcessHandle> {
+    let mut handle = start_mediamtx(launcher, bin_path, config_path, sink)?;
+
+    let probes = mediamtx_probes(config_path);
+    if let Err(e) = wait_until_ready(&mut handle, &probes, readiness).await {
+        if let Err(kill_err) = kill_process(&mut handle) {
+            log::error!("Error stopping MediaMTX: {}", kill_err);
+        }
+        return Err(e.context("MediaMTX did not become ready"));
+    }
+
+    Ok(handle)
 }
 
 /// Inicia Cloudflared túnel con archivo de configuración
-pub fn start_cloudflared(bin_path: &Path, config_path: &Path) -> Result<ProcessHandle> {
+pub fn start_cloudflared(
+    launcher: &dyn ProcessLauncher,
+    bin_path: &Path,
+    config_path: &Path,
+    sink: &LogSink,
+) -> Result<ProcessHandle> {
     log::info!("Starting Cloudflared from: {:?} with config: {:?}", bin_path, config_path);
     
-    let mut cmd = Command::new(bin_path);
-    cmd.arg("tunnel")
+    let spec = LaunchSpec::new("cloudflared", bin_path)
+        .arg("tunnel")
         .arg("--config")
         .arg(config_path)
-        .arg("run")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
-
-    let child = cmd.spawn()
+        .arg("run");
+    let handle = spawn_process(launcher, &spec, sink)
         .context("Failed to spawn Cloudflared process")?;
 
-    log::info!("Cloudflared started with PID: {:?}", child.id());
-    Ok(ProcessHandle::new("cloudflared".to_string(), child))
+    log::info!("Cloudflared started with PID: {:?}", handle.pid);
+    Ok(handle)
 }
 
 /// Inicia Cloudflared en modo Quick Tunnel (sin configuración previa)
 /// Esto crea un túnel temporal con URL automática tipo: https://random-words.trycloudflare.com
-pub fn start_cloudflared_quick_tunnel(bin_path: &Path, local_port: u16) -> Result<ProcessHandle> {
+pub fn start_cloudflared_quick_tunnel(
+    launcher: &dyn ProcessLauncher,
+    bin_path: &Path,
+    local_port: u16,
+    sink: &LogSink,
+) -> Result<ProcessHandle> {
     log::info!("Starting Cloudflared Quick Tunnel to localhost:{}", local_port);
     
     // cloudflared tunnel --url http://localhost:8888
-    let mut cmd = Command::new(bin_path);
-    cmd.arg("tunnel")
+    let spec = LaunchSpec::new("cloudflared", bin_path)
+        .arg("tunnel")
         .arg("--url")
-        .arg(format!("http://localhost:{}", local_port))
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
-
-    let child = cmd.spawn()
+        .arg(format!("http://localhost:{}", local_port));
+    let handle = spawn_process(launcher, &spec, sink)
         .context("Failed to spawn Cloudflared quick tunnel")?;
 
-    log::info!("Cloudflared Quick Tunnel started with PID: {:?}", child.id());
-    log::info!("Check stderr output for the tunnel URL (*.trycloudflare.com)");
-    Ok(ProcessHandle::new("cloudflared-quick".to_string(), child))
+    log::info!("Cloudflared Quick Tunnel started with PID: {:?}", handle.pid);
+    log::info!("Check the cloudflared logs for the tunnel URL (*.trycloudflare.com)");
+    Ok(handle)
+}
+
+/// Modo en el que se lanza cloudflared
+#[derive(Debug, Clone)]
+pub enum CloudflaredMode {
+    /// Túnel existente con archivo de configuración
+    Named { config_path: PathBuf },
+    /// Quick Tunnel hacia un puerto local
+    Quick { local_port: u16 },
+}
+
+/// Todo lo necesario para (re)lanzar cloudflared
+#[derive(Debug, Clone)]
+pub struct CloudflaredLaunch {
+    pub bin_path: PathBuf,
+    pub mode: CloudflaredMode,
+}
+
+/// Inicia cloudflared en el modo indicado
+pub fn launch_cloudflared(
+    launcher: &dyn ProcessLauncher,
+    launch: &CloudflaredLaunch,
+    sink: &LogSink,
+) -> Result<ProcessHandle> {
+    match &launch.mode {
+        CloudflaredMode::Named { config_path } => start_cloudflared(launcher, &launch.bin_path, config_path, sink),
+        CloudflaredMode::Quick { local_port } => {
+            start_cloudflared_quick_tunnel(launcher, &launch.bin_path, *local_port, sink)
+        }
+    }
 }
 
 /// Inicia FFmpeg para una cámara específica
 pub fn start_ffmpeg(
+    launcher: &dyn ProcessLauncher,
     bin_path: &Path,
     camera_id: &str,
     rtsp_url: &str,
     encoding: &crate::app_state::EncodingMode,
     quality: &crate::app_state::QualityPreset,
     audio_mode: &crate::app_state::AudioMode,
+    capabilities: Option<&FfmpegCapabilities>,
+    sink: &LogSink,
 ) -> Result<ProcessHandle> {
     log::info!("Starting FFmpeg for camera: {}", camera_id);
     
     let output_url = format!("rtsp://localhost:8554/{}", camera_id);
-    let args = build_ffmpeg_args(rtsp_url, &output_url, encoding, quality, audio_mode);
+    let args = build_ffmpeg_args(rtsp_url, &output_url, encoding, quality, audio_mode, capabilities)?;
     
-    let mut cmd = Command::new(bin_path);
-    cmd.args(&args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+    let spec = LaunchSpec::new(format!("ffmpeg-{}", camera_id), bin_path)
+        .args(args)
+        .with_stdin();
+    let handle = spawn_process(launcher, &spec, sink)
+        .with_context(|| format!("Failed to spawn FFmpeg for camera {}", camera_id))?
+        .with_quit_input("q");
 
-    let child = cmd.spawn()
-        .with_context(|| format!("Failed to spawn FFmpeg for camera {}", camera_id))?;
-
-    log::info!("FFmpeg for {} started with PID: {:?}", camera_id, child.id());
-    Ok(ProcessHandle::new(format!("ffmpeg-{}", camera_id), child))
+    log::info!("FFmpeg for {} started with PID: {:?}", camera_id, handle.pid);
+    Ok(handle)
 }
 
 /// Genera argumentos para FFmpeg según configuración
@@ -95,7 +170,15 @@ fn build_ffmpeg_args(
     encoding: &crate::app_state::EncodingMode,
     quality: &crate::app_state::QualityPreset,
     audio_mode: &crate::app_state::AudioMode,
-) -> Vec<String> {
+    capabilities: Option<&FfmpegCapabilities>,
+) -> Result<Vec<String>> {
+    // Sin capacidades conocidas se asume un FFmpeg completo (libx264, aac)
+    if let Some(caps) = capabilities {
+        if !caps.output_protocols.is_empty() && !caps.has_output_protocol("tcp") {
+            return Err(UnsupportedByFfmpeg("TCP output (needed to publish RTSP)".to_string()).into());
+        }
+    }
+
     let mut args = vec![
         "-rtsp_transport".to_string(),
         "tcp".to_string(),
@@ -127,6 +210,16 @@ fn build_ffmpeg_args(
                 crate::app_state::QualityPreset::High => ("1920:1080", "5000k", "30", "60", "medium"),
             };
 
+            // Sin libx264 se usa otro encoder H.264 (ej. de hardware), sin preset
+            let video_encoder = match capabilities {
+                Some(caps) => caps.h264_encoder()
+                    .ok_or_else(|| UnsupportedByFfmpeg("any H.264 encoder".to_string()))?,
+                None => "libx264",
+            };
+            if video_encoder != "libx264" {
+                log::warn!("libx264 not available, transcoding with {}", video_encoder);
+            }
+
             let max_bitrate = format!("{}k", (bitrate.trim_end_matches('k').parse::<u32>().unwrap() as f32 * 1.2) as u32);
             let bufsize = format!("{}k", (bitrate.trim_end_matches('k').parse::<u32>().unwrap() * 4));
 
@@ -134,9 +227,12 @@ fn build_ffmpeg_args(
                 "-vf".to_string(),
                 format!("scale={}:force_original_aspect_ratio=decrease,fps={}", resolution, fps),
                 "-c:v".to_string(),
-                "libx264".to_string(),
-                "-preset".to_string(),
-                preset.to_string(),
+                video_encoder.to_string(),
+            ]);
+            if video_encoder == "libx264" {
+                args.extend(vec!["-preset".to_string(), preset.to_string()]);
+            }
+            args.extend(vec![
                 "-b:v".to_string(),
                 bitrate.to_string(),
                 "-maxrate".to_string(),
@@ -162,6 +258,9 @@ fn build_ffmpeg_args(
                     args.extend(vec!["-c:a".to_string(), "copy".to_string()]);
                 }
                 crate::app_state::AudioMode::Transcode => {
+                    if capabilities.map_or(false, |caps| !caps.has_encoder("aac")) {
+                        return Err(UnsupportedByFfmpeg("the aac audio encoder".to_string()).into());
+                    }
                     args.extend(vec![
                         "-c:a".to_string(),
                         "aac".to_string(),
@@ -184,19 +283,169 @@ fn build_ffmpeg_args(
         output_url.to_string(),
     ]);
 
-    args
+    Ok(args)
+}
+
+/// Etapa en la que terminó un proceso al detenerlo
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StopStage {
+    /// El proceso ya había terminado por su cuenta
+    AlreadyExited,
+    /// Cerró tras pedirlo por stdin
+    Quit,
+    /// Cerró tras SIGTERM
+    Terminate,
+    /// Hubo que forzarlo con SIGKILL
+    Kill,
+}
+
+/// Resultado de detener un proceso
+#[derive(Debug, Clone)]
+pub struct StopOutcome {
+    pub stage: StopStage,
+    pub exit_status: Option<ExitStatus>,
+}
+
+/// Detiene un proceso de manera ordenada: primero `quit_input` por stdin (si el
+/// proceso lo soporta), luego SIGTERM (salvo en Windows) y por último SIGKILL,
+/// esperando los tiempos de la `ShutdownPolicy` entre etapas.
+pub async fn stop_process(handle: &mut ProcessHandle, policy: &ShutdownPolicy) -> Result<StopOutcome> {
+    let Some(mut child) = handle.child.take() else {
+        return Ok(StopOutcome { stage: StopStage::AlreadyExited, exit_status: handle.exit_status });
+    };
+
+    log::info!("Stopping process: {}", handle.name);
+
+    if let Some(status) = child.try_wait()? {
+        log::info!("Process {} had already exited ({})", handle.name, status);
+        handle.exit_status = Some(status);
+        remove_pid_file(&handle.name);
+        return Ok(StopOutcome { stage: StopStage::AlreadyExited, exit_status: Some(status) });
+    }
+
+    // 1. Pedir cierre por stdin
+    if let Some(input) = handle.quit_input {
+        if let Some(mut stdin) = child.take_stdin() {
+            log::info!("{}: sending '{}' on stdin", handle.name, input);
+            if let Err(e) = stdin.write_all(input.as_bytes()).and_then(|_| stdin.flush()) {
+                log::warn!("{}: failed to write quit input: {}", handle.name, e);
+            }
+            drop(stdin);
+
+            if let Some(status) = wait_for_exit(child.as_mut(), policy.quit_timeout_ms).await? {
+                return Ok(finish_stop(handle, StopStage::Quit, status));
+            }
+            log::warn!("{}: did not quit within {}ms", handle.name, policy.quit_timeout_ms);
+        }
+    }
+
+    // 2. SIGTERM (no existe en Windows, ver `CAN_TERMINATE`)
+    if CAN_TERMINATE {
+        log::info!("{}: sending terminate signal", handle.name);
+        match child.terminate() {
+            Ok(()) => {
+                if let Some(status) = wait_for_exit(child.as_mut(), policy.terminate_grace_ms).await? {
+                    return Ok(finish_stop(handle, StopStage::Terminate, status));
+                }
+                log::warn!("{}: still running {}ms after terminate signal", handle.name, policy.terminate_grace_ms);
+            }
+            Err(e) => {
+                log::warn!("{}: failed to send terminate signal: {}", handle.name, e);
+            }
+        }
+    }
+
+    // 3. SIGKILL
+    log::warn!("{}: killing process", handle.name);
+    if let Err(e) = child.kill() {
+        log::error!("Failed to kill process {}: {}", handle.name, e);
+        handle.child = Some(child);
+        return Err(anyhow::anyhow!("Failed to kill process: {}", e));
+    }
+    let status = child.wait()?;
+    Ok(finish_stop(handle, StopStage::Kill, status))
+}
+
+fn finish_stop(handle: &mut ProcessHandle, stage: StopStage, status: ExitStatus) -> StopOutcome {
+    log::info!("Process {} stopped at stage {:?} ({})", handle.name, stage, status);
+    handle.exit_status = Some(status);
+    remove_pid_file(&handle.name);
+    StopOutcome { stage, exit_status: Some(status) }
+}
+
+/// Espera hasta `timeout_ms` a que el proceso termine
+async fn wait_for_exit(child: &mut dyn ManagedChild, timeout_ms: u64) -> Result<Option<ExitStatus>> {
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Ok(None);
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Si la plataforma tiene una etapa de terminación entre el cierre por stdin
+/// y el kill. En Windows no: `taskkill` sin `/F` solo cierra procesos con
+/// ventana, y FFmpeg, MediaMTX y cloudflared corren como consola sin ventana
+/// ("can only be terminated forcefully"). Ahí se pasa directo al kill.
+pub(crate) const CAN_TERMINATE: bool = cfg!(unix);
+
+#[cfg(unix)]
+pub(crate) fn send_terminate(pid: u32) -> Result<()> {
+    // SAFETY: kill(2) no accede a memoria; un pid inválido solo devuelve error
+    let result = unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub(crate) fn send_terminate(pid: u32) -> Result<()> {
+    Err(anyhow::anyhow!("No terminate signal on Windows for PID {}, kill it instead", pid))
+}
+
+/// Fuerza la terminación de un PID que no es hijo directo (ej. un huérfano)
+#[cfg(unix)]
+pub(crate) fn send_kill(pid: u32) -> Result<()> {
+    // SAFETY: kill(2) no accede a memoria; un pid inválido solo devuelve error
+    let result = unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub(crate) fn send_kill(pid: u32) -> Result<()> {
+    let status = Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run taskkill")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("taskkill exited with {}", status));
+    }
+    Ok(())
 }
 
-/// Detiene un proceso de manera segura
-pub fn stop_process(handle: &mut ProcessHandle) -> Result<()> {
+/// Mata un proceso inmediatamente, sin etapas previas (para procesos colgados)
+pub fn kill_process(handle: &mut ProcessHandle) -> Result<()> {
     if let Some(mut child) = handle.child.take() {
-        log::info!("Stopping process: {}", handle.name);
-        
-        // Intentar kill
+        log::info!("Killing process: {}", handle.name);
+
         match child.kill() {
             Ok(_) => {
-                let _ = child.wait();
-                log::info!("Process {} stopped successfully", handle.name);
+                if let Ok(status) = child.wait() {
+                    handle.exit_status = Some(status);
+                }
+                remove_pid_file(&handle.name);
+                log::info!("Process {} killed", handle.name);
                 Ok(())
             }
             Err(e) => {
@@ -213,8 +462,10 @@ pub fn stop_process(handle: &mut ProcessHandle) -> Result<()> {
 pub fn is_process_running(handle: &mut ProcessHandle) -> bool {
     if let Some(child) = &mut handle.child {
         match child.try_wait() {
-            Ok(Some(_status)) => {
+            Ok(Some(status)) => {
                 // Proceso terminó
+                handle.exit_status = Some(status);
+                remove_pid_file(&handle.name);
                 false
             }
             Ok(None) => {
@@ -231,78 +482,6 @@ pub fn is_process_running(handle: &mut ProcessHandle) -> bool {
     }
 }
 
-/// Monitorea y reinicia un proceso según política de reconexión
-pub async fn monitor_and_reconnect<F, Fut>(
-    name: String,
-    policy: ReconnectPolicy,
-    stats: &mut ReconnectStats,
-    status: &mut ProcessStatus,
-    start_fn: F,
-) -> Result<ProcessHandle>
-where
-    F: Fn() -> Fut,
-    Fut: std::future::Future<Output = Result<ProcessHandle>>,
-{
-    if !policy.enabled {
-        return start_fn().await;
-    }
-
-    let mut attempts = 0;
-    let mut current_delay = policy.retry_delay_ms;
-
-    loop {
-        match start_fn().await {
-            Ok(handle) => {
-                stats.record_success();
-                *status = ProcessStatus::Running;
-                log::info!("{} started successfully", name);
-                return Ok(handle);
-            }
-            Err(e) => {
-                attempts += 1;
-                stats.record_restart();
-                *status = ProcessStatus::Reconnecting;
-                
-                log::warn!(
-                    "{} failed to start (attempt {}/{}): {}",
-                    name, attempts, policy.max_retries, e
-                );
-
-                if attempts >= policy.max_retries {
-                    *status = ProcessStatus::Failed;
-                    return Err(anyhow::anyhow!(
-                        "{} failed after {} attempts",
-                        name, attempts
-                    ));
-                }
-
-                // Backoff exponencial
-                log::info!("{} waiting {}ms before retry...", name, current_delay);
-                sleep(Duration::from_millis(current_delay)).await;
-                
-                current_delay = (current_delay as f32 * policy.backoff_multiplier) as u64;
-                current_delay = current_delay.min(policy.max_delay_ms);
-            }
-        }
-    }
-}
-
-/// Obtiene la ruta de un binario desde resources
-pub fn get_binary_path(app_handle: &tauri::AppHandle, binary_name: &str) -> Result<PathBuf> {
-    let resource_path = app_handle
-        .path()
-        .resource_dir()
-        .context("Failed to get resource dir")?;
-    
-    let bin_path = resource_path.join("bin").join(binary_name);
-    
-    if !bin_path.exists() {
-        return Err(anyhow::anyhow!("Binary not found: {:?}", bin_path));
-    }
-    
-    Ok(bin_path)
-}
-
 /// Obtiene la ruta de configuración
 pub fn get_config_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf> {
     let config_dir = app_handle
@@ -0,0 +1,91 @@
+/// Línea de progreso de FFmpeg ya parseada
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FfmpegProgress {
+    pub frame: u64,
+    pub fps: f32,
+    pub bitrate_kbps: Option<f32>,
+    pub speed: Option<f32>,
+    pub drop: u64,
+    pub dup: u64,
+}
+
+/// Parsea una línea de progreso de FFmpeg, por ejemplo:
+///
+/// `frame= 1234 fps= 25 q=-1.0 size=  12345kB time=00:00:49.36 bitrate=2048.5kbits/s dup=0 drop=3 speed=1.00x`
+///
+/// Devuelve `None` si la línea no es de progreso.
+pub fn parse_progress_line(line: &str) -> Option<FfmpegProgress> {
+    let line = line.trim_start();
+    if !line.starts_with("frame=") {
+        return None;
+    }
+
+    // FFmpeg alinea los valores con espacios después del '=' ("frame=  12")
+    let mut normalized = String::with_capacity(line.len());
+    let mut after_equals = false;
+    for c in line.chars() {
+        if after_equals && c == ' ' {
+            continue;
+        }
+        after_equals = c == '=';
+        normalized.push(c);
+    }
+
+    let mut progress = FfmpegProgress::default();
+    let mut has_frame = false;
+
+    for pair in normalized.split_whitespace() {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+
+        match key {
+            "frame" => {
+                progress.frame = value.parse().ok()?;
+                has_frame = true;
+            }
+            "fps" => progress.fps = value.parse().unwrap_or(0.0),
+            "bitrate" => progress.bitrate_kbps = value.trim_end_matches("kbits/s").parse().ok(),
+            "speed" => progress.speed = value.trim_end_matches('x').parse().ok(),
+            "drop" => progress.drop = value.parse().unwrap_or(0),
+            "dup" => progress.dup = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    has_frame.then_some(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transcode_progress() {
+        let line = "frame= 1234 fps= 25 q=28.0 size=   12345kB time=00:00:49.36 bitrate=2048.5kbits/s dup=2 drop=3 speed=1.01x";
+        let progress = parse_progress_line(line).unwrap();
+
+        assert_eq!(progress.frame, 1234);
+        assert_eq!(progress.fps, 25.0);
+        assert_eq!(progress.bitrate_kbps, Some(2048.5));
+        assert_eq!(progress.speed, Some(1.01));
+        assert_eq!(progress.dup, 2);
+        assert_eq!(progress.drop, 3);
+    }
+
+    #[test]
+    fn test_parse_progress_with_missing_values() {
+        let line = "frame=    0 fps=0.0 q=-1.0 size=N/A time=N/A bitrate=N/A speed=N/A";
+        let progress = parse_progress_line(line).unwrap();
+
+        assert_eq!(progress.frame, 0);
+        assert_eq!(progress.bitrate_kbps, None);
+        assert_eq!(progress.speed, None);
+    }
+
+    #[test]
+    fn test_ignore_non_progress_lines() {
+        assert!(parse_progress_line("Input #0, rtsp, from 'rtsp://192.168.1.240:554/stream':").is_none());
+        assert!(parse_progress_line("  Stream #0:0: Video: h264 (High), yuv420p, 1920x1080, 25 fps").is_none());
+    }
+}
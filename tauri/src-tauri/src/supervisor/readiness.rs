@@ -0,0 +1,247 @@
+use crate::app_state::{ProcessHandle, ReadinessPolicy};
+use crate::supervisor::process_manager::is_process_running;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout, Instant};
+
+/// Chequeo que indica si un servicio ya acepta conexiones
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadinessProbe {
+    /// El puerto acepta conexiones TCP
+    Tcp { addr: String },
+    /// El endpoint HTTP responde (cualquier status menor a 500)
+    Http { url: String },
+}
+
+impl ReadinessProbe {
+    async fn check(&self, client: &reqwest::Client) -> Result<()> {
+        match self {
+            ReadinessProbe::Tcp { addr } => {
+                TcpStream::connect(addr).await
+                    .with_context(|| format!("{} not accepting connections", addr))?;
+                Ok(())
+            }
+            ReadinessProbe::Http { url } => {
+                let response = client.get(url).send().await
+                    .with_context(|| format!("{} not responding", url))?;
+                if response.status().is_server_error() {
+                    return Err(anyhow::anyhow!("{} returned {}", url, response.status()));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ReadinessProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadinessProbe::Tcp { addr } => write!(f, "tcp://{}", addr),
+            ReadinessProbe::Http { url } => write!(f, "{}", url),
+        }
+    }
+}
+
+/// Espera hasta que todos los probes del servicio respondan.
+///
+/// Falla si se cumple `policy.timeout_ms` o si el proceso termina mientras
+/// se espera. Devuelve cuánto tardó el servicio en estar listo.
+pub async fn wait_until_ready(
+    handle: &mut ProcessHandle,
+    probes: &[ReadinessProbe],
+    policy: &ReadinessPolicy,
+) -> Result<Duration> {
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(policy.timeout_ms);
+    let interval = Duration::from_millis(policy.interval_ms);
+    let client = reqwest::Client::builder()
+        .timeout(interval.max(Duration::from_millis(500)))
+        .build()
+        .context("Failed to build HTTP client")?;
+
+    log::info!("Waiting for {} to be ready ({} probes)", handle.name, probes.len());
+
+    let mut pending: Vec<&ReadinessProbe> = probes.iter().collect();
+    let mut last_error = None;
+
+    while !pending.is_empty() {
+        if !is_process_running(handle) {
+            return Err(anyhow::anyhow!(
+                "{} exited while waiting for readiness ({:?})",
+                handle.name, handle.exit_status
+            ));
+        }
+
+        let mut still_pending = Vec::new();
+        for probe in pending {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match timeout(remaining, probe.check(&client)).await {
+                Ok(Ok(())) => log::info!("{}: {} is ready", handle.name, probe),
+                Ok(Err(e)) => {
+                    last_error = Some(e);
+                    still_pending.push(probe);
+                }
+                Err(_) => still_pending.push(probe),
+            }
+        }
+        pending = still_pending;
+
+        if pending.is_empty() {
+            break;
+        }
+
+        if Instant::now() >= deadline {
+            let waiting_on: Vec<String> = pending.iter().map(|p| p.to_string()).collect();
+            return Err(anyhow::anyhow!(
+                "{} not ready after {}ms (waiting on {}): {}",
+                handle.name,
+                policy.timeout_ms,
+                waiting_on.join(", "),
+                last_error.map(|e| e.to_string()).unwrap_or_else(|| "timed out".to_string())
+            ));
+        }
+
+        sleep(interval).await;
+    }
+
+    let elapsed = started.elapsed();
+    log::info!("{} ready after {}ms", handle.name, elapsed.as_millis());
+    Ok(elapsed)
+}
+
+/// Probes de MediaMTX según su `mediamtx.yml`: el puerto RTSP y, si la API
+/// está habilitada, su endpoint HTTP.
+pub fn mediamtx_probes(config_path: &Path) -> Vec<ReadinessProbe> {
+    let config: serde_yaml::Value = match std::fs::read_to_string(config_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| serde_yaml::from_str(&content).map_err(anyhow::Error::from))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("Failed to read MediaMTX config {:?}, using default ports: {}", config_path, e);
+            serde_yaml::Value::Null
+        }
+    };
+
+    let rtsp_address = config.get("rtspAddress")
+        .and_then(|v| v.as_str())
+        .unwrap_or(":8554");
+    let mut probes = vec![ReadinessProbe::Tcp { addr: local_address(rtsp_address) }];
+
+    // MediaMTX acepta tanto `yes` como `true`
+    let api_enabled = match config.get("api") {
+        Some(serde_yaml::Value::Bool(enabled)) => *enabled,
+        Some(serde_yaml::Value::String(value)) => value == "yes" || value == "true",
+        _ => false,
+    };
+    if api_enabled {
+        let api_address = config.get("apiAddress")
+            .and_then(|v| v.as_str())
+            .unwrap_or(":9997");
+        probes.push(ReadinessProbe::Http {
+            url: format!("http://{}/v3/config/global/get", local_address(api_address)),
+        });
+    } else {
+        log::info!("MediaMTX API disabled in config, skipping API readiness probe");
+    }
+
+    probes
+}
+
+/// Convierte una dirección de escucha (`:8554`, `0.0.0.0:8554`) en una
+/// dirección local a la que conectarse
+fn local_address(listen: &str) -> String {
+    match listen.rsplit_once(':') {
+        Some(("", port)) | Some(("0.0.0.0", port)) | Some(("[::]", port)) => format!("127.0.0.1:{}", port),
+        _ => listen.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supervisor::fake::{FakeLauncher, FakeScript};
+    use crate::supervisor::launcher::{LaunchSpec, ProcessLauncher};
+    use std::net::TcpListener;
+
+    fn mediamtx_handle(launcher: &FakeLauncher) -> ProcessHandle {
+        let child = launcher.spawn(&LaunchSpec::new("mediamtx", "mediamtx")).unwrap();
+        ProcessHandle::new("mediamtx".to_string(), child)
+    }
+
+    /// Puerto local en el que no escucha nadie
+    fn closed_port() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_local_address() {
+        assert_eq!(local_address(":8554"), "127.0.0.1:8554");
+        assert_eq!(local_address("0.0.0.0:9997"), "127.0.0.1:9997");
+        assert_eq!(local_address("[::]:8888"), "127.0.0.1:8888");
+        assert_eq!(local_address("192.168.1.20:8554"), "192.168.1.20:8554");
+    }
+
+    #[test]
+    fn test_mediamtx_probes() {
+        let dir = std::env::temp_dir().join(format!("stream-agent-readiness-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let probes = |yaml: &str| {
+            let path = dir.join("mediamtx.yml");
+            std::fs::write(&path, yaml).unwrap();
+            mediamtx_probes(&path)
+        };
+        let rtsp = |addr: &str| ReadinessProbe::Tcp { addr: addr.to_string() };
+        let api = |addr: &str| ReadinessProbe::Http { url: format!("http://{}/v3/config/global/get", addr) };
+
+        // Sin archivo: puertos por defecto y sin API
+        assert_eq!(mediamtx_probes(&dir.join("missing.yml")), vec![rtsp("127.0.0.1:8554")]);
+        assert_eq!(probes("rtspAddress: :9554\napi: no\n"), vec![rtsp("127.0.0.1:9554")]);
+        assert_eq!(probes("api: yes\n"), vec![rtsp("127.0.0.1:8554"), api("127.0.0.1:9997")]);
+        assert_eq!(
+            probes("api: true\napiAddress: 0.0.0.0:9998\n"),
+            vec![rtsp("127.0.0.1:8554"), api("127.0.0.1:9998")]
+        );
+        assert_eq!(probes("api: false\napiAddress: :9998\n"), vec![rtsp("127.0.0.1:8554")]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_ready() {
+        let launcher = FakeLauncher::new();
+        let policy = ReadinessPolicy { timeout_ms: 300, interval_ms: 20 };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = ReadinessProbe::Tcp { addr: listener.local_addr().unwrap().to_string() };
+        let mut handle = mediamtx_handle(&launcher);
+        assert!(wait_until_ready(&mut handle, &[open.clone()], &policy).await.is_ok());
+
+        // Un puerto cerrado agota el timeout e informa qué faltó
+        let closed = ReadinessProbe::Tcp { addr: closed_port() };
+        let started = Instant::now();
+        let error = wait_until_ready(&mut handle, &[open, closed.clone()], &policy).await.unwrap_err();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        let message = error.to_string();
+        assert!(message.contains("not ready after 300ms"), "{}", message);
+        assert!(message.contains(&closed.to_string()), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_ready_fails_when_process_exits() {
+        let launcher = FakeLauncher::new();
+        launcher.script("mediamtx", FakeScript::exits_after(50, 1));
+        let policy = ReadinessPolicy { timeout_ms: 5000, interval_ms: 20 };
+
+        let mut handle = mediamtx_handle(&launcher);
+        let started = Instant::now();
+        let error = wait_until_ready(&mut handle, &[ReadinessProbe::Tcp { addr: closed_port() }], &policy)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("exited while waiting"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,71 +1,26 @@
-use crate::app_state::{ReconnectPolicy, ReconnectStats};
-use std::time::Duration;
-use tokio::time::sleep;
+use crate::app_state::{ProcessStatus, ReconnectPolicy, ReconnectStats};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use tauri::Emitter;
 
-/// Ejecuta un reinicio con backoff exponencial
-pub async fn reconnect_with_backoff<F, Fut, T>(
-    name: &str,
-    policy: &ReconnectPolicy,
-    stats: &mut ReconnectStats,
-    reconnect_fn: F,
-) -> Result<T, anyhow::Error>
-where
-    F: Fn() -> Fut,
-    Fut: std::future::Future<Output = Result<T, anyhow::Error>>,
-{
-    if !policy.enabled {
-        return reconnect_fn().await;
-    }
-
-    // Resetear contador si ha pasado suficiente tiempo estable
-    if stats.should_reset_counter(policy) {
-        log::info!("{}: Resetting reconnect counter after stable period", name);
-        stats.consecutive_failures = 0;
-    }
-
-    let mut attempts = 0;
-    let mut current_delay = policy.retry_delay_ms;
-
-    loop {
-        match reconnect_fn().await {
-            Ok(result) => {
-                stats.record_success();
-                if attempts > 0 {
-                    log::info!("{}: Reconnected successfully after {} attempts", name, attempts);
-                }
-                return Ok(result);
-            }
-            Err(e) => {
-                attempts += 1;
-                stats.record_restart();
-                
-                log::warn!(
-                    "{}: Reconnect attempt {}/{} failed: {}",
-                    name, attempts, policy.max_retries, e
-                );
-
-                if attempts >= policy.max_retries {
-                    log::error!("{}: Max retries ({}) reached, giving up", name, policy.max_retries);
-                    return Err(anyhow::anyhow!(
-                        "{} failed after {} attempts: {}",
-                        name, attempts, e
-                    ));
-                }
-
-                // Calcular delay con backoff
-                let delay_ms = calculate_backoff_delay(attempts, policy);
-                log::info!("{}: Waiting {}ms before retry {}/{}...", 
-                    name, delay_ms, attempts + 1, policy.max_retries);
-                
-                sleep(Duration::from_millis(delay_ms)).await;
-                current_delay = delay_ms;
-            }
-        }
-    }
+/// Cómo se aleatoriza el delay entre reintentos, para que procesos que fallan
+/// juntos (ej. todas las cámaras cuando cae MediaMTX) no reintenten a la vez
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JitterMode {
+    /// Delay exponencial exacto
+    None,
+    /// Aleatorio entre 0 y el delay exponencial
+    #[default]
+    Full,
+    /// Aleatorio entre `retry_delay_ms` y el triple del delay anterior
+    Decorrelated,
 }
 
 /// Calcula el delay con backoff exponencial
-fn calculate_backoff_delay(attempt: u32, policy: &ReconnectPolicy) -> u64 {
+pub fn calculate_backoff_delay(attempt: u32, policy: &ReconnectPolicy) -> u64 {
     let base_delay = policy.retry_delay_ms as f32;
     let multiplier = policy.backoff_multiplier;
     let max_delay = policy.max_delay_ms;
@@ -76,6 +31,104 @@ fn calculate_backoff_delay(attempt: u32, policy: &ReconnectPolicy) -> u64 {
     delay_ms.min(max_delay)
 }
 
+/// Delay del reintento número `attempt` según el `JitterMode` de la política.
+///
+/// `random` es un valor en `[0, 1)`; `previous_delay_ms` es el delay del
+/// reintento anterior (solo lo usa el jitter decorrelacionado).
+pub fn jittered_delay(
+    attempt: u32,
+    previous_delay_ms: Option<u64>,
+    policy: &ReconnectPolicy,
+    random: f64,
+) -> u64 {
+    let random = random.clamp(0.0, 1.0);
+
+    match policy.jitter {
+        JitterMode::None => calculate_backoff_delay(attempt, policy),
+        JitterMode::Full => (calculate_backoff_delay(attempt, policy) as f64 * random) as u64,
+        JitterMode::Decorrelated => {
+            let base = policy.retry_delay_ms;
+            let upper = previous_delay_ms.unwrap_or(base).saturating_mul(3).max(base);
+            let delay = base + ((upper - base) as f64 * random) as u64;
+            delay.min(policy.max_delay_ms)
+        }
+    }
+}
+
+/// Valor pseudoaleatorio en `[0, 1)`. Alcanza para repartir reintentos; no
+/// usar para nada criptográfico.
+pub fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(Utc::now().timestamp_nanos_opt().unwrap_or_default() as u128);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Momento del ciclo de reintentos que se reporta a la UI
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RetryPhase {
+    /// Falló y quedó agendado un reintento
+    Scheduled,
+    /// Se está relanzando el proceso
+    Attempting,
+    /// El relanzamiento funcionó
+    Recovered,
+    /// Entró en cuarentena hasta `next_retry_at`
+    Quarantined,
+    /// No quedan reintentos
+    GaveUp,
+    /// El reintento agendado se descartó (ej. el usuario lo detuvo)
+    Cancelled,
+}
+
+/// Payload del evento `retry-event`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryEventPayload {
+    /// `mediamtx`, `cloudflared` o el id de la cámara
+    pub component: String,
+    pub phase: RetryPhase,
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: Option<u64>,
+    pub next_retry_at: Option<DateTime<Utc>>,
+}
+
+/// Fase a reportar según el estado que devolvió `ReconnectStats::record_failure`
+pub fn failure_phase(status: &ProcessStatus) -> RetryPhase {
+    match status {
+        ProcessStatus::Failed => RetryPhase::GaveUp,
+        ProcessStatus::Quarantined => RetryPhase::Quarantined,
+        _ => RetryPhase::Scheduled,
+    }
+}
+
+/// Emite un `retry-event` con el estado de reintentos del componente
+pub fn emit_retry_event(
+    app_handle: Option<&tauri::AppHandle>,
+    component: &str,
+    phase: RetryPhase,
+    stats: &ReconnectStats,
+    policy: &ReconnectPolicy,
+) {
+    let Some(app_handle) = app_handle else {
+        return;
+    };
+
+    let scheduled = matches!(phase, RetryPhase::Scheduled | RetryPhase::Quarantined);
+    let payload = RetryEventPayload {
+        component: component.to_string(),
+        phase,
+        attempt: stats.consecutive_failures,
+        max_retries: policy.max_retries,
+        delay_ms: if scheduled { stats.last_delay_ms } else { None },
+        next_retry_at: if scheduled { stats.next_retry_at } else { None },
+    };
+    if let Err(e) = app_handle.emit("retry-event", payload) {
+        log::warn!("Failed to emit retry-event: {}", e);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,12 +136,10 @@ mod tests {
     #[test]
     fn test_backoff_calculation() {
         let policy = ReconnectPolicy {
-            enabled: true,
             max_retries: 5,
-            retry_delay_ms: 3000,
-            backoff_multiplier: 2.0,
-            max_delay_ms: 60000,
-            reset_counter_after_ms: 300000,
+            jitter: JitterMode::None,
+            quarantine_after_restarts: 0,
+            ..ReconnectPolicy::default()
         };
 
         assert_eq!(calculate_backoff_delay(1, &policy), 3000);
@@ -101,43 +152,173 @@ mod tests {
         assert_eq!(calculate_backoff_delay(10, &policy), 60000);
     }
 
-    #[tokio::test]
-    async fn test_reconnect_success_on_first_try() {
-        let policy = ReconnectPolicy::default();
+    #[test]
+    fn test_reconnect_success_resets_retry_state() {
+        let policy = ReconnectPolicy {
+            jitter: JitterMode::None,
+            ..ReconnectPolicy::default()
+        };
         let mut stats = ReconnectStats::default();
         
-        let result = reconnect_with_backoff(
-            "test",
-            &policy,
-            &mut stats,
-            || async { Ok(42) }
-        ).await;
+        stats.record_failure(&policy);
+        stats.record_failure(&policy);
+        assert_eq!(stats.consecutive_failures, 2);
+        assert_eq!(stats.last_delay_ms, Some(6000));
+        
+        stats.record_success();
         
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 42);
         assert_eq!(stats.consecutive_failures, 0);
+        assert_eq!(stats.last_delay_ms, None);
+        assert!(stats.last_stable_time.is_some());
+        // El historial de reinicios se conserva
+        assert_eq!(stats.restarts, 2);
+        
+        // El backoff vuelve a empezar desde el delay base
+        assert_eq!(stats.record_failure(&policy), ProcessStatus::Reconnecting);
+        assert_eq!(stats.consecutive_failures, 1);
+        assert_eq!(stats.last_delay_ms, Some(policy.retry_delay_ms));
     }
 
-    #[tokio::test]
-    async fn test_reconnect_max_retries() {
+    #[test]
+    fn test_reconnect_max_retries() {
         let policy = ReconnectPolicy {
-            enabled: true,
             max_retries: 3,
             retry_delay_ms: 10,
             backoff_multiplier: 1.5,
             max_delay_ms: 1000,
             reset_counter_after_ms: 10000,
+            jitter: JitterMode::None,
+            quarantine_after_restarts: 0,
+            ..ReconnectPolicy::default()
         };
         let mut stats = ReconnectStats::default();
         
-        let result = reconnect_with_backoff(
-            "test",
-            &policy,
-            &mut stats,
-            || async { Err(anyhow::anyhow!("Always fails")) }
-        ).await;
+        // El primer fallo y cada reintento fallido cuentan como reinicio
+        for _ in 0..policy.max_retries {
+            assert_eq!(stats.record_failure(&policy), ProcessStatus::Reconnecting);
+            assert!(stats.next_retry_at.is_some());
+        }
+        assert_eq!(stats.record_failure(&policy), ProcessStatus::Failed);
         
-        assert!(result.is_err());
-        assert_eq!(stats.restarts, 3);
+        assert_eq!(stats.restarts, 4);
+        assert_eq!(stats.next_retry_at, None);
+        assert!(!stats.is_retry_due());
+    }
+
+    #[test]
+    fn test_reset_after_stable() {
+        let policy = ReconnectPolicy {
+            jitter: JitterMode::None,
+            ..ReconnectPolicy::default()
+        };
+        let mut stats = ReconnectStats::default();
+        stats.record_failure(&policy);
+        stats.record_failure(&policy);
+        assert_eq!(stats.last_delay_ms, Some(6000));
+
+        // Todavía no pasó el período estable
+        assert!(!stats.reset_if_stable(policy.reset_counter_after_ms - 1, &policy));
+        assert_eq!(stats.consecutive_failures, 2);
+
+        assert!(stats.reset_if_stable(policy.reset_counter_after_ms, &policy));
+        assert_eq!(stats.consecutive_failures, 0);
+        assert_eq!(stats.last_delay_ms, None);
+        assert_eq!(stats.restarts, 2);
+
+        // Tras el reset el backoff vuelve a empezar desde el delay base
+        stats.record_failure(&policy);
+        assert_eq!(stats.last_delay_ms, Some(policy.retry_delay_ms));
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        let full = ReconnectPolicy { jitter: JitterMode::Full, ..ReconnectPolicy::default() };
+        let decorrelated = ReconnectPolicy { jitter: JitterMode::Decorrelated, ..ReconnectPolicy::default() };
+
+        for attempt in 1..=12 {
+            let ceiling = calculate_backoff_delay(attempt, &full);
+            assert_eq!(jittered_delay(attempt, None, &full, 0.0), 0);
+            assert!(jittered_delay(attempt, None, &full, 0.999) <= ceiling);
+        }
+
+        // Decorrelado: entre el delay base y el triple del anterior, sin pasar max_delay
+        assert_eq!(jittered_delay(1, None, &decorrelated, 0.0), 3000);
+        assert_eq!(jittered_delay(1, None, &decorrelated, 0.5), 6000);
+        assert_eq!(jittered_delay(2, Some(10000), &decorrelated, 0.0), 3000);
+        assert_eq!(jittered_delay(2, Some(10000), &decorrelated, 1.0), 30000);
+        assert_eq!(jittered_delay(5, Some(50000), &decorrelated, 1.0), decorrelated.max_delay_ms);
+
+        for _ in 0..200 {
+            let random = random_unit();
+            assert!((0.0..1.0).contains(&random));
+
+            let mut stats = ReconnectStats::default();
+            stats.record_failure(&full);
+            let delay = stats.last_delay_ms.unwrap();
+            assert!(delay <= full.retry_delay_ms);
+        }
+    }
+
+    #[test]
+    fn test_cancel_retry() {
+        let policy = ReconnectPolicy {
+            retry_delay_ms: 0,
+            jitter: JitterMode::None,
+            ..ReconnectPolicy::default()
+        };
+        let mut stats = ReconnectStats::default();
+
+        assert_eq!(stats.record_failure(&policy), ProcessStatus::Reconnecting);
+        assert!(stats.is_retry_due());
+
+        stats.cancel_retry();
+        assert!(!stats.is_retry_due());
+        assert_eq!(stats.next_retry_at, None);
+        // Cancelar no borra el historial de fallos
+        assert_eq!(stats.consecutive_failures, 1);
+    }
+
+    #[test]
+    fn test_quarantine_after_crash_loop() {
+        let policy = ReconnectPolicy {
+            retry_delay_ms: 0,
+            jitter: JitterMode::None,
+            quarantine_after_restarts: 3,
+            ..ReconnectPolicy::default()
+        };
+        let mut stats = ReconnectStats::default();
+
+        for _ in 0..3 {
+            assert_eq!(stats.record_failure(&policy), ProcessStatus::Reconnecting);
+        }
+        assert_eq!(stats.record_failure(&policy), ProcessStatus::Quarantined);
+        let quarantine = stats.quarantine.clone().expect("quarantine info");
+        assert_eq!(stats.next_retry_at, Some(quarantine.until));
+        assert!(!stats.is_retry_due());
+
+        // Tras el cooldown se intenta una vez; si falla vuelve a cuarentena
+        stats.begin_probation();
+        assert!(stats.quarantine.is_none());
+        assert_eq!(stats.record_failure(&policy), ProcessStatus::Quarantined);
+
+        // Si se estabiliza, la cuarentena queda olvidada
+        stats.begin_probation();
+        assert!(stats.reset_if_stable(policy.reset_counter_after_ms, &policy));
+        assert!(!stats.on_probation);
+        assert_eq!(stats.record_failure(&policy), ProcessStatus::Reconnecting);
+    }
+
+    #[test]
+    fn test_policy_validation() {
+        assert!(ReconnectPolicy::default().validate().is_ok());
+
+        let no_delay = ReconnectPolicy { retry_delay_ms: 0, ..ReconnectPolicy::default() };
+        assert!(no_delay.validate().is_err());
+
+        let shrinking = ReconnectPolicy { backoff_multiplier: 0.5, ..ReconnectPolicy::default() };
+        assert!(shrinking.validate().is_err());
+
+        let capped_below_base = ReconnectPolicy { retry_delay_ms: 5000, max_delay_ms: 1000, ..ReconnectPolicy::default() };
+        assert!(capped_below_base.validate().is_err());
     }
 }
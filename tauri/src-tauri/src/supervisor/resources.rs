@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Consumo de un proceso en una muestra
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    pub pid: u32,
+    /// CPU desde la muestra anterior, relativo a un núcleo (puede pasar de
+    /// 100 con varios hilos). `None` en la primera muestra de un proceso.
+    pub cpu_percent: Option<f32>,
+    pub rss_bytes: u64,
+    /// `None` si la plataforma no lo informa
+    pub threads: Option<u32>,
+    pub open_fds: Option<u32>,
+    pub sampled_at: DateTime<Utc>,
+}
+
+/// Lectura cruda de la plataforma
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawUsage {
+    /// CPU acumulada (usuario + sistema) desde que arrancó el proceso
+    pub cpu_time: Duration,
+    pub rss_bytes: u64,
+    pub threads: Option<u32>,
+    pub open_fds: Option<u32>,
+}
+
+/// Muestrea un proceso en cada tick del watchdog y guarda un historial corto
+#[derive(Debug)]
+pub struct ResourceMonitor {
+    current: Option<ResourceUsage>,
+    history: VecDeque<ResourceUsage>,
+    max_samples: usize,
+    /// Muestra anterior (pid, CPU acumulada, instante) para calcular el %
+    last_cpu: Option<(u32, Duration, Instant)>,
+}
+
+impl ResourceMonitor {
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            history: VecDeque::new(),
+            max_samples: 150, // 5 minutos con el watchdog cada 2s
+            last_cpu: None,
+        }
+    }
+
+    /// Toma una muestra de `pid`. Si no se puede leer (plataforma sin
+    /// soporte, proceso terminado) queda sin valor actual.
+    pub fn sample(&mut self, pid: u32) {
+        let Some(raw) = read_process_usage(pid) else {
+            self.clear();
+            return;
+        };
+
+        let now = Instant::now();
+        let cpu_percent = match self.last_cpu {
+            Some((last_pid, last_time, at)) if last_pid == pid => {
+                let wall = now.duration_since(at).as_secs_f32();
+                (wall > 0.0).then(|| raw.cpu_time.saturating_sub(last_time).as_secs_f32() / wall * 100.0)
+            }
+            _ => None,
+        };
+        self.last_cpu = Some((pid, raw.cpu_time, now));
+
+        let usage = ResourceUsage {
+            pid,
+            cpu_percent,
+            rss_bytes: raw.rss_bytes,
+            threads: raw.threads,
+            open_fds: raw.open_fds,
+            sampled_at: Utc::now(),
+        };
+        self.history.push_back(usage.clone());
+        if self.history.len() > self.max_samples {
+            self.history.pop_front();
+        }
+        self.current = Some(usage);
+    }
+
+    /// Olvida el proceso actual (se detuvo o murió). El historial se conserva.
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.last_cpu = None;
+    }
+
+    pub fn current(&self) -> Option<&ResourceUsage> {
+        self.current.as_ref()
+    }
+
+    /// Historial de los últimos `window_secs` segundos (o completo si es `None`)
+    pub fn history(&self, window_secs: Option<u64>) -> Vec<ResourceUsage> {
+        match window_secs {
+            Some(secs) => {
+                let since = Utc::now() - chrono::Duration::seconds(secs as i64);
+                self.history.iter()
+                    .filter(|s| s.sampled_at >= since)
+                    .cloned()
+                    .collect()
+            }
+            None => self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Lee el consumo de un proceso desde `/proc/<pid>`
+#[cfg(target_os = "linux")]
+pub fn read_process_usage(pid: u32) -> Option<RawUsage> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (cpu_ticks, threads, rss_pages) = parse_proc_stat(&stat)?;
+
+    // SAFETY: sysconf solo consulta constantes del sistema
+    let (ticks_per_sec, page_size) = unsafe {
+        (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE))
+    };
+    if ticks_per_sec <= 0 || page_size <= 0 {
+        return None;
+    }
+
+    let open_fds = std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .ok()
+        .map(|entries| entries.count() as u32);
+
+    Some(RawUsage {
+        cpu_time: Duration::from_secs_f64(cpu_ticks as f64 / ticks_per_sec as f64),
+        rss_bytes: rss_pages * page_size as u64,
+        threads: Some(threads),
+        open_fds,
+    })
+}
+
+/// Lee el consumo con `ps` (macOS y otros Unix sin `/proc`). No informa
+/// hilos ni descriptores abiertos.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn read_process_usage(pid: u32) -> Option<RawUsage> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=,time=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.split_whitespace();
+    let rss_kb: u64 = fields.next()?.parse().ok()?;
+    let cpu_time = parse_ps_time(fields.next()?)?;
+
+    Some(RawUsage { cpu_time, rss_bytes: rss_kb * 1024, threads: None, open_fds: None })
+}
+
+/// Sin backend en esta plataforma todavía
+#[cfg(not(unix))]
+pub fn read_process_usage(_pid: u32) -> Option<RawUsage> {
+    None
+}
+
+/// Extrae (ticks de CPU usuario + sistema, hilos, páginas residentes) de
+/// `/proc/<pid>/stat`
+pub fn parse_proc_stat(stat: &str) -> Option<(u64, u32, u64)> {
+    // El nombre del comando va entre paréntesis y puede contener espacios o
+    // paréntesis: los campos numéricos empiezan después del último ')'
+    let rest = stat.get(stat.rfind(')')? + 1..)?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+
+    // `fields[0]` es el campo 3 (estado): utime=14, stime=15, num_threads=20, rss=24
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    let threads: u32 = fields.get(17)?.parse().ok()?;
+    let rss_pages: u64 = fields.get(21)?.parse().ok()?;
+
+    Some((utime + stime, threads, rss_pages))
+}
+
+/// Parsea el tiempo de CPU de `ps`: `[[dd-]hh:]mm:ss[.ss]`
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parse_ps_time(value: &str) -> Option<Duration> {
+    let (days, clock) = match value.split_once('-') {
+        Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+        None => (0.0, value),
+    };
+    let seconds = clock.split(':')
+        .try_fold(0.0, |total, part| part.parse::<f64>().ok().map(|v| total * 60.0 + v))?;
+    Some(Duration::from_secs_f64(days * 86400.0 + seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_stat() {
+        let stat = "4242 (ffmpeg (cam 1)) S 1 4242 4242 0 -1 4194304 5120 0 0 0 1500 250 0 0 20 0 7 0 123456 734003200 25600 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 3 0 0 0 0 0\n";
+        assert_eq!(parse_proc_stat(stat), Some((1750, 7, 25600)));
+        assert_eq!(parse_proc_stat("4242 (ffmpeg) S 1"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sample_own_process() {
+        let mut monitor = ResourceMonitor::new();
+        let pid = std::process::id();
+
+        monitor.sample(pid);
+        let first = monitor.current().cloned().expect("own process should be readable");
+        assert!(first.cpu_percent.is_none());
+        assert!(first.rss_bytes > 0);
+        assert!(first.threads.unwrap_or(0) >= 1);
+
+        monitor.sample(pid);
+        assert!(monitor.current().unwrap().cpu_percent.is_some());
+        assert_eq!(monitor.history(None).len(), 2);
+
+        monitor.clear();
+        assert!(monitor.current().is_none());
+        assert_eq!(monitor.history(None).len(), 2);
+    }
+}
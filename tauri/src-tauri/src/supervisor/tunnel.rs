@@ -0,0 +1,136 @@
+use crate::app_state::TunnelStatus;
+use crate::supervisor::output::LogSink;
+use chrono::Utc;
+use std::sync::{Arc, Mutex as StdMutex};
+use tauri::Emitter;
+
+/// Evento relevante en la salida de cloudflared
+#[derive(Debug, Clone, PartialEq)]
+pub enum TunnelEvent {
+    /// `Registered tunnel connection connIndex=0 ...`
+    Registered,
+    /// `Unregistered tunnel connection` o `Connection terminated`
+    Unregistered,
+    /// URL pública del Quick Tunnel (`https://*.trycloudflare.com`)
+    Url(String),
+}
+
+/// Reconoce los eventos de conexión en una línea de log de cloudflared
+pub fn parse_tunnel_event(line: &str) -> Option<TunnelEvent> {
+    if line.contains("Registered tunnel connection") {
+        return Some(TunnelEvent::Registered);
+    }
+    if line.contains("Unregistered tunnel connection") || line.contains("Connection terminated") {
+        return Some(TunnelEvent::Unregistered);
+    }
+
+    let start = line.find("https://")?;
+    let url: String = line[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '|')
+        .collect();
+    url.ends_with(".trycloudflare.com").then_some(TunnelEvent::Url(url))
+}
+
+/// Prepara el sink de logs de cloudflared: las líneas se siguen guardando, pero
+/// además actualizan el `TunnelStatus` y emiten `tunnel-status-changed` cuando
+/// el túnel se conecta o desconecta.
+pub fn tunnel_sink(
+    sink: &LogSink,
+    tunnel: Arc<StdMutex<TunnelStatus>>,
+    app_handle: Option<tauri::AppHandle>,
+) -> LogSink {
+    if let Ok(mut status) = tunnel.lock() {
+        status.connections = 0;
+        status.connected = false;
+    }
+
+    sink.with_line_handler(move |line| {
+        let Some(event) = parse_tunnel_event(line) else {
+            return false;
+        };
+
+        let snapshot = {
+            let Ok(mut status) = tunnel.lock() else {
+                return false;
+            };
+            let was_connected = status.connected;
+
+            match event {
+                TunnelEvent::Registered => status.connections += 1,
+                TunnelEvent::Unregistered => status.connections = status.connections.saturating_sub(1),
+                TunnelEvent::Url(url) => {
+                    log::info!("Quick Tunnel URL: {}", url);
+                    status.url = Some(url);
+                }
+            }
+            status.connected = status.connections > 0;
+
+            if status.connected == was_connected {
+                return false;
+            }
+            status.updated_at = Some(Utc::now());
+            status.clone()
+        };
+
+        if snapshot.connected {
+            log::info!("Cloudflared tunnel connected");
+        } else {
+            log::warn!("Cloudflared tunnel disconnected");
+        }
+        if let Some(app_handle) = &app_handle {
+            emit_tunnel_status(app_handle, &snapshot);
+        }
+
+        false
+    })
+}
+
+/// Marca el túnel como desconectado (por ejemplo, al morir cloudflared)
+pub fn mark_tunnel_disconnected(tunnel: &StdMutex<TunnelStatus>, app_handle: Option<&tauri::AppHandle>) {
+    let snapshot = {
+        let Ok(mut status) = tunnel.lock() else {
+            return;
+        };
+        if !status.connected {
+            return;
+        }
+        status.connected = false;
+        status.connections = 0;
+        status.updated_at = Some(Utc::now());
+        status.clone()
+    };
+
+    log::warn!("Cloudflared tunnel disconnected");
+    if let Some(app_handle) = app_handle {
+        emit_tunnel_status(app_handle, &snapshot);
+    }
+}
+
+fn emit_tunnel_status(app_handle: &tauri::AppHandle, status: &TunnelStatus) {
+    if let Err(e) = app_handle.emit("tunnel-status-changed", status.clone()) {
+        log::warn!("Failed to emit tunnel-status-changed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tunnel_events() {
+        assert_eq!(
+            parse_tunnel_event("2024-05-01T10:00:00Z INF Registered tunnel connection connIndex=0 connection=abc event=0 ip=198.41.192.7 location=eze01 protocol=quic"),
+            Some(TunnelEvent::Registered)
+        );
+        assert_eq!(
+            parse_tunnel_event("2024-05-01T10:05:00Z INF Unregistered tunnel connection connIndex=0 event=0 ip=198.41.192.7"),
+            Some(TunnelEvent::Unregistered)
+        );
+        assert_eq!(
+            parse_tunnel_event("2024-05-01T10:00:00Z INF |  https://cool-words-here.trycloudflare.com                                   |"),
+            Some(TunnelEvent::Url("https://cool-words-here.trycloudflare.com".to_string()))
+        );
+        assert_eq!(parse_tunnel_event("2024-05-01T10:00:00Z INF Starting metrics server on 127.0.0.1:20241/metrics"), None);
+    }
+}
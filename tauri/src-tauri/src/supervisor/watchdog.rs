@@ -0,0 +1,246 @@
+use crate::agent::{cameras_problem, transition_agent};
+use crate::app_state::{
+    AgentState, AppState, ProcessHandle, ProcessStatus, ServiceRuntime, ServiceStatusPayload,
+};
+use crate::cameras::{restart_active_cameras, supervise_cameras};
+use crate::supervisor::output::LogSink;
+use crate::supervisor::process_manager::{
+    is_process_running, launch_cloudflared, launch_mediamtx, CloudflaredLaunch,
+};
+use crate::supervisor::reconnect::{emit_retry_event, failure_phase, RetryPhase};
+use crate::supervisor::tunnel::{mark_tunnel_disconnected, tunnel_sink};
+use anyhow::Result;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// Intervalo entre chequeos del watchdog
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_millis(2000);
+
+/// Rutas que necesita el watchdog para relanzar procesos
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// Normalmente `WATCHDOG_INTERVAL`; los tests usan uno más corto
+    pub interval: Duration,
+    pub mediamtx_bin: PathBuf,
+    pub mediamtx_config: PathBuf,
+    /// `None` si cloudflared no está disponible
+    pub cloudflared: Option<CloudflaredLaunch>,
+}
+
+/// Handle a la tarea de supervisión en segundo plano
+#[derive(Debug)]
+pub struct WatchdogHandle {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl WatchdogHandle {
+    /// Detiene el watchdog y espera a que la tarea termine
+    pub async fn stop(self) {
+        let _ = self.shutdown.send(true);
+        if let Err(e) = self.task.await {
+            log::error!("Watchdog task ended abnormally: {}", e);
+        }
+    }
+}
+
+/// Resultado de supervisar un servicio en un tick
+#[derive(Debug, PartialEq)]
+enum ServiceHealth {
+    Healthy,
+    Down,
+    /// Volvió a estar listo en este tick
+    Recovered,
+}
+
+/// Lanza el watchdog que vigila MediaMTX, cloudflared y los procesos FFmpeg
+/// de las cámaras y los reinicia según su `ReconnectPolicy`.
+pub fn spawn_watchdog(
+    state: AppState,
+    app_handle: Option<tauri::AppHandle>,
+    config: WatchdogConfig,
+) -> WatchdogHandle {
+    let (shutdown, mut shutdown_rx) = watch::channel(false);
+    let sink = LogSink::new(state.logs.clone(), app_handle.clone());
+
+    let task = tokio::spawn(async move {
+        log::info!("Watchdog started (interval: {}ms)", config.interval.as_millis());
+        let mut interval = tokio::time::interval(config.interval);
+        let app_handle = app_handle.as_ref();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let mediamtx = supervise_service("mediamtx", &state.mediamtx, app_handle, || async {
+                        let readiness = state.config.lock().await.readiness_policy.clone();
+                        launch_mediamtx(state.launcher.as_ref(), &config.mediamtx_bin, &config.mediamtx_config, &sink, &readiness).await
+                    }).await;
+
+                    let mut problems = Vec::new();
+                    if mediamtx == ServiceHealth::Down {
+                        problems.push("MediaMTX is down".to_string());
+                    }
+
+                    if let Some(launch) = &config.cloudflared {
+                        let cloudflared = supervise_service("cloudflared", &state.cloudflared, app_handle, || async {
+                            let sink = tunnel_sink(&sink, state.tunnel.clone(), app_handle.cloned());
+                            launch_cloudflared(state.launcher.as_ref(), launch, &sink)
+                        }).await;
+                        if cloudflared != ServiceHealth::Healthy {
+                            mark_tunnel_disconnected(&state.tunnel, app_handle);
+                        }
+                        if cloudflared == ServiceHealth::Down {
+                            problems.push("Cloudflared is down".to_string());
+                        }
+                    }
+
+                    // Solo se toma el mapa para enviar los pedidos; cada cámara
+                    // hace el chequeo en su propia tarea
+                    let cameras = state.cameras.lock().await;
+                    match mediamtx {
+                        ServiceHealth::Healthy => supervise_cameras(&cameras),
+                        ServiceHealth::Recovered => restart_active_cameras(&cameras),
+                        // Sin MediaMTX las cámaras no pueden publicar: esperar
+                        ServiceHealth::Down => {}
+                    }
+                    // Estado publicado en el tick anterior: el chequeo recién
+                    // pedido se refleja en la próxima vuelta
+                    problems.extend(cameras_problem(&cameras));
+                    drop(cameras);
+
+                    update_agent_health(&state, app_handle, problems).await;
+                }
+                _ = shutdown_rx.changed() => {
+                    break;
+                }
+            }
+        }
+
+        log::info!("Watchdog stopped");
+    });
+
+    WatchdogHandle { shutdown, task }
+}
+
+/// Verifica que un servicio de soporte siga vivo y lo relanza con backoff si
+/// cayó. `launch` solo se invoca cuando corresponde un nuevo intento, sin
+/// retener el lock del servicio mientras se ejecuta.
+async fn supervise_service<F, Fut>(
+    name: &str,
+    service: &Mutex<ServiceRuntime>,
+    app_handle: Option<&tauri::AppHandle>,
+    launch: F,
+) -> ServiceHealth
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<ProcessHandle>>,
+{
+    {
+        let mut runtime = service.lock().await;
+
+        match runtime.status {
+            ProcessStatus::Running => {
+                let alive = runtime.process.as_mut().map_or(false, is_process_running);
+                if alive {
+                    let uptime_ms = runtime.uptime_secs() * 1000;
+                    let policy = runtime.reconnect_policy.clone();
+                    if runtime.stats.reset_if_stable(uptime_ms, &policy) {
+                        log::info!("{} stable, resetting failure counter", name);
+                    }
+                    if let Some(pid) = runtime.process.as_ref().and_then(|p| p.pid) {
+                        runtime.resources.sample(pid);
+                    }
+                    return ServiceHealth::Healthy;
+                }
+
+                let exit_status = runtime.process.as_ref().and_then(|p| p.exit_status);
+                log::error!("{} died unexpectedly ({:?})", name, exit_status);
+                if let Some(handle) = runtime.process.take() {
+                    runtime.stats.total_uptime_secs += handle.uptime().as_secs();
+                }
+                runtime.resources.clear();
+                let policy = runtime.reconnect_policy.clone();
+                runtime.status = runtime.stats.record_failure(&policy);
+                emit_service_status(app_handle, name, &runtime.status);
+                emit_retry_event(app_handle, name, failure_phase(&runtime.status), &runtime.stats, &policy);
+            }
+            ProcessStatus::Reconnecting | ProcessStatus::Quarantined => {}
+            ProcessStatus::Stopped | ProcessStatus::Failed => return ServiceHealth::Down,
+        }
+
+        if !runtime.stats.is_retry_due() {
+            return ServiceHealth::Down;
+        }
+
+        if runtime.status == ProcessStatus::Quarantined {
+            log::info!("{} quarantine cooldown ended, trying once more", name);
+            runtime.stats.begin_probation();
+        }
+
+        log::info!(
+            "Restarting {} (attempt {}/{})",
+            name, runtime.stats.consecutive_failures, runtime.reconnect_policy.max_retries
+        );
+        emit_retry_event(app_handle, name, RetryPhase::Attempting, &runtime.stats, &runtime.reconnect_policy);
+    }
+
+    let result = launch().await;
+
+    let mut runtime = service.lock().await;
+    match result {
+        Ok(handle) => {
+            log::info!("{} restarted", name);
+            runtime.set_running(handle);
+            emit_service_status(app_handle, name, &runtime.status);
+            emit_retry_event(app_handle, name, RetryPhase::Recovered, &runtime.stats, &runtime.reconnect_policy);
+            ServiceHealth::Recovered
+        }
+        Err(e) => {
+            log::error!("{} restart failed: {:#}", name, e);
+            let policy = runtime.reconnect_policy.clone();
+            runtime.status = runtime.stats.record_failure(&policy);
+            if runtime.status == ProcessStatus::Failed {
+                log::error!("{}: max retries reached, giving up", name);
+                emit_service_status(app_handle, name, &runtime.status);
+            }
+            emit_retry_event(app_handle, name, failure_phase(&runtime.status), &runtime.stats, &policy);
+            ServiceHealth::Down
+        }
+    }
+}
+
+/// Pasa el agente entre `Running` y `Degraded` según los problemas detectados
+async fn update_agent_health(state: &AppState, app_handle: Option<&tauri::AppHandle>, problems: Vec<String>) {
+    let (next, reason) = if problems.is_empty() {
+        (AgentState::Running, "All components healthy".to_string())
+    } else {
+        (AgentState::Degraded, problems.join("; "))
+    };
+
+    {
+        let lifecycle = state.lifecycle.lock().await;
+        let unchanged = lifecycle.state == next && (next == AgentState::Running || lifecycle.reason == reason);
+        if !lifecycle.state.is_active() || unchanged {
+            return;
+        }
+    }
+
+    // Puede perder la carrera con `shutdown_agent`, que ya pasó a `Stopping`
+    if let Err(e) = transition_agent(state, app_handle, next, reason).await {
+        log::debug!("Skipping agent health update: {}", e);
+    }
+}
+
+fn emit_service_status(app_handle: Option<&tauri::AppHandle>, service: &str, status: &ProcessStatus) {
+    let Some(app_handle) = app_handle else {
+        return;
+    };
+    let payload = ServiceStatusPayload { service: service.to_string(), status: status.as_str().to_string() };
+    if let Err(e) = app_handle.emit("service-status-changed", payload) {
+        log::warn!("Failed to emit service-status-changed: {}", e);
+    }
+}
@@ -5,12 +5,26 @@ import "./App.css";
 
 interface AgentStatus {
   running: boolean;
+  state: "stopped" | "starting" | "running" | "degraded" | "stopping";
+  stateReason: string;
   mediamtx_running: boolean;
   cloudflared_running: boolean;
   cameras_running: number;
   cameras_total: number;
   tunnel_url: string | null;
   uptime_secs: number;
+  mediamtxResources: ResourceUsage | null;
+  cloudflaredResources: ResourceUsage | null;
+  configWarnings: string[];
+}
+
+interface ResourceUsage {
+  pid: number;
+  cpuPercent: number | null;
+  rssBytes: number;
+  threads: number | null;
+  openFds: number | null;
+  sampledAt: string;
 }
 
 interface CameraInfo {
@@ -24,8 +38,43 @@ interface CameraInfo {
   status: string;
   restarts: number;
   last_restart: string | null;
+  nextRetryAt: string | null;
+  quarantine: QuarantineInfo | null;
+  lastFailure: FailureInfo | null;
+  resources: ResourceUsage | null;
+}
+
+interface QuarantineInfo {
+  reason: string;
+  since: string;
+  until: string;
 }
 
+interface FailureInfo {
+  reason: string;
+  message: string | null;
+  exitStatus: string | null;
+  at: string;
+}
+
+const FAILURE_LABELS: Record<string, string> = {
+  unauthorized: "Usuario o contraseña incorrectos",
+  connectionRefused: "La cámara rechazó la conexión",
+  hostUnreachable: "No se puede llegar a la cámara",
+  streamNotFound: "La ruta del stream no existe",
+  unsupportedCodec: "Códec no soportado",
+  publishRejected: "MediaMTX rechazó el stream",
+  unknown: "Error desconocido",
+};
+
+const AGENT_STATE_LABELS: Record<string, string> = {
+  stopped: "Offline",
+  starting: "Iniciando...",
+  running: "Online",
+  degraded: "Online (con fallas)",
+  stopping: "Deteniendo...",
+};
+
 interface NewCamera {
   id: string;
   name: string;
@@ -36,6 +85,53 @@ interface NewCamera {
   audioMode: "disabled" | "copy" | "transcode";
 }
 
+// Campos editables de config.json; el resto se devuelve tal como llegó
+interface AgentConfig {
+  serverUrl: string;
+  locationId: string;
+  locationName: string;
+  autoStartAgent: boolean;
+  autoStartCameras: boolean;
+  tunnelEnabled: boolean;
+  tunnelMode: "auto" | "quick" | "named";
+  tunnelName: string;
+  tunnelId: string | null;
+  tunnelHostname: string | null;
+  [key: string]: unknown;
+}
+
+interface FieldError {
+  field: string;
+  message: string;
+}
+
+interface ConfigUpdateError {
+  message: string;
+  fields: FieldError[];
+}
+
+interface ImportIssue {
+  camera: string | null;
+  field: string;
+  message: string;
+}
+
+interface LegacyImport {
+  camerasFormat: string | null;
+  configFormat: string | null;
+  cameras: NewCamera[];
+  config: AgentConfig | null;
+  issues: ImportIssue[];
+  dryRun: boolean;
+}
+
+const LEGACY_FORMAT_LABELS: Record<string, string> = {
+  electronCameras: "stream-agent-electron (cámaras)",
+  electronConfig: "stream-agent-electron (configuración)",
+  urlMap: "servidor Node (id → URL)",
+  nodeRegistry: "servidor Node (registros)",
+};
+
 type TabType = "status" | "cameras" | "logs" | "settings";
 
 function App() {
@@ -55,6 +151,10 @@ function App() {
     quality: "medium",
     audioMode: "copy",
   });
+  const [agentConfig, setAgentConfig] = useState<AgentConfig | null>(null);
+  const [configErrors, setConfigErrors] = useState<FieldError[]>([]);
+  const [legacyPaths, setLegacyPaths] = useState({ cameras: "", config: "" });
+  const [legacyImport, setLegacyImport] = useState<LegacyImport | null>(null);
 
   // Auto-clear messages
   useEffect(() => {
@@ -94,12 +194,27 @@ function App() {
     }
   }, []);
 
+  // Load agent config
+  const loadAgentConfig = useCallback(async () => {
+    try {
+      const config = await invoke<AgentConfig>("get_agent_config");
+      setAgentConfig(config);
+      setConfigErrors([]);
+    } catch (error) {
+      console.error("Failed to load agent config:", error);
+    }
+  }, []);
+
+  useEffect(() => {
+    if (activeTab === "settings") loadAgentConfig();
+  }, [activeTab, loadAgentConfig]);
+
   // Initial load and event listeners
   useEffect(() => {
     loadStatus();
     loadCameras();
 
-    const unlisten1 = listen("agent-status-changed", () => {
+    const unlisten1 = listen("agent-state-changed", () => {
       loadStatus();
     });
 
@@ -157,6 +272,51 @@ function App() {
     }
   };
 
+  const handleSaveAgentConfig = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!agentConfig) return;
+    setLoading(true);
+    try {
+      const saved = await invoke<AgentConfig>("update_agent_config", { config: agentConfig });
+      setAgentConfig(saved);
+      setConfigErrors([]);
+      setMessage({ type: "success", text: "Configuración guardada" });
+    } catch (error) {
+      const updateError = error as ConfigUpdateError;
+      setConfigErrors(updateError.fields ?? []);
+      setMessage({ type: "error", text: updateError.message ?? `${error}` });
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const handleLegacyImport = async (dryRun: boolean) => {
+    setLoading(true);
+    try {
+      const result = await invoke<LegacyImport>("import_legacy_config", {
+        camerasPath: legacyPaths.cameras || null,
+        configPath: legacyPaths.config || null,
+        dryRun,
+      });
+      setLegacyImport(result);
+      if (!dryRun) {
+        setMessage({ type: "success", text: `Importadas ${result.cameras.length} cámaras` });
+        await loadAgentConfig();
+      }
+    } catch (error) {
+      setMessage({ type: "error", text: `${error}` });
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const fieldError = (field: string) => {
+    const errors = configErrors.filter((e) => e.field === field);
+    return errors.length > 0 && (
+      <span className="field-error">{errors.map((e) => e.message).join(", ")}</span>
+    );
+  };
+
   // Camera controls
   const handleStartCamera = async (id: string) => {
     setLoading(true);
@@ -197,6 +357,19 @@ function App() {
     }
   };
 
+  const handleClearQuarantine = async (id: string) => {
+    setLoading(true);
+    try {
+      const result = await invoke<string>("clear_camera_quarantine", { id });
+      setMessage({ type: "success", text: result });
+      await loadCameras();
+    } catch (error) {
+      setMessage({ type: "error", text: `${error}` });
+    } finally {
+      setLoading(false);
+    }
+  };
+
   const handleRemoveCamera = async (id: string) => {
     if (!confirm(`¿Eliminar cámara ${id}?`)) return;
     setLoading(true);
@@ -246,6 +419,13 @@ function App() {
     return `${hours}h ${minutes}m ${secs}s`;
   };
 
+  const formatResources = (usage: ResourceUsage): string => {
+    const cpu = usage.cpuPercent === null ? "–" : `${usage.cpuPercent.toFixed(0)}%`;
+    const memory = `${(usage.rssBytes / (1024 * 1024)).toFixed(0)} MB`;
+    const threads = usage.threads === null ? "" : ` · ${usage.threads} hilos`;
+    return `CPU ${cpu} · ${memory}${threads}`;
+  };
+
   const getStatusClass = (running: boolean) => running ? "status-running" : "status-stopped";
 
   return (
@@ -257,7 +437,7 @@ function App() {
           {agentStatus && (
             <>
               <span className={`indicator ${agentStatus.running ? "on" : "off"}`} />
-              <span>{agentStatus.running ? "Online" : "Offline"}</span>
+              <span title={agentStatus.stateReason}>{AGENT_STATE_LABELS[agentStatus.state] ?? agentStatus.state}</span>
             </>
           )}
         </div>
@@ -297,6 +477,11 @@ function App() {
           {message.type === "error" ? "❌" : message.type === "success" ? "✅" : "ℹ️"} {message.text}
         </div>
       )}
+      {agentStatus?.configWarnings?.map((warning) => (
+        <div key={warning} className="message message-warning">
+          ⚠️ {warning}
+        </div>
+      ))}
 
       {/* Main Content */}
       <main className="content">
@@ -334,6 +519,9 @@ function App() {
                       {agentStatus.mediamtx_running ? "RUNNING" : "STOPPED"}
                     </div>
                     <div className="service-desc">Servidor RTSP/HLS</div>
+                    {agentStatus.mediamtxResources && (
+                      <div className="service-resources">{formatResources(agentStatus.mediamtxResources)}</div>
+                    )}
                   </div>
                   <div className="service-card">
                     <div className="service-icon">🌐</div>
@@ -342,6 +530,9 @@ function App() {
                       {agentStatus.cloudflared_running ? "RUNNING" : "STOPPED"}
                     </div>
                     <div className="service-desc">Túnel de acceso</div>
+                    {agentStatus.cloudflaredResources && (
+                      <div className="service-resources">{formatResources(agentStatus.cloudflaredResources)}</div>
+                    )}
                   </div>
                   <div className="service-card">
                     <div className="service-icon">📹</div>
@@ -501,6 +692,22 @@ function App() {
                           <div><strong>Calidad:</strong> {camera.quality}</div>
                           <div><strong>Audio:</strong> {camera.audioMode}</div>
                           <div><strong>Restarts:</strong> {camera.restarts}</div>
+                          {camera.resources && (
+                            <div><strong>Recursos:</strong> {formatResources(camera.resources)}</div>
+                          )}
+                          {camera.quarantine && (
+                            <div className="camera-quarantine">
+                              <strong>Cuarentena:</strong> {camera.quarantine.reason}
+                              <br />
+                              Próximo intento: {new Date(camera.quarantine.until).toLocaleTimeString()}
+                            </div>
+                          )}
+                          {camera.lastFailure && (
+                            <div className="camera-failure" title={camera.lastFailure.message ?? undefined}>
+                              <strong>Último fallo:</strong> {FAILURE_LABELS[camera.lastFailure.reason] ?? camera.lastFailure.reason}
+                              {" "}({new Date(camera.lastFailure.at).toLocaleTimeString()})
+                            </div>
+                          )}
                         </div>
                       </div>
                       <div className="camera-actions">
@@ -528,6 +735,16 @@ function App() {
                         >
                           🔄
                         </button>
+                        {camera.status === "quarantined" && (
+                          <button
+                            onClick={() => handleClearQuarantine(camera.id)}
+                            disabled={loading}
+                            className="btn btn-sm btn-reconnect"
+                            title="Quitar cuarentena"
+                          >
+                            🔓
+                          </button>
+                        )}
                         <button
                           onClick={() => handleRemoveCamera(camera.id)}
                           disabled={loading}
@@ -573,6 +790,175 @@ function App() {
         {/* Settings Tab */}
         {activeTab === "settings" && (
           <div className="tab-content">
+            {agentConfig && (
+              <section className="card">
+                <h2>General</h2>
+                <form className="add-camera-form" onSubmit={handleSaveAgentConfig}>
+                  <div className="form-row">
+                    <div className="form-group">
+                      <label>Nombre de la ubicación</label>
+                      <input
+                        type="text"
+                        value={agentConfig.locationName}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, locationName: e.target.value })}
+                      />
+                      {fieldError("locationName")}
+                    </div>
+                    <div className="form-group">
+                      <label>ID de ubicación</label>
+                      <input
+                        type="text"
+                        value={agentConfig.locationId}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, locationId: e.target.value })}
+                      />
+                      {fieldError("locationId")}
+                    </div>
+                  </div>
+                  <div className="form-group">
+                    <label>URL del servidor</label>
+                    <input
+                      type="text"
+                      value={agentConfig.serverUrl}
+                      onChange={(e) => setAgentConfig({ ...agentConfig, serverUrl: e.target.value })}
+                    />
+                    {fieldError("serverUrl")}
+                  </div>
+                  <div className="form-row">
+                    <div className="form-group">
+                      <label>Modo del túnel</label>
+                      <select
+                        value={agentConfig.tunnelMode}
+                        disabled={!agentConfig.tunnelEnabled}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, tunnelMode: e.target.value as AgentConfig["tunnelMode"] })}
+                      >
+                        <option value="auto">Automático</option>
+                        <option value="quick">Quick Tunnel</option>
+                        <option value="named">Túnel con nombre</option>
+                      </select>
+                    </div>
+                    <div className="form-group">
+                      <label>Hostname del túnel</label>
+                      <input
+                        type="text"
+                        placeholder="camaras.ejemplo.com"
+                        value={agentConfig.tunnelHostname ?? ""}
+                        disabled={!agentConfig.tunnelEnabled || agentConfig.tunnelMode === "quick"}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, tunnelHostname: e.target.value || null })}
+                      />
+                      {fieldError("tunnelHostname")}
+                    </div>
+                  </div>
+                  <div className="form-group checkbox">
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={agentConfig.tunnelEnabled}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, tunnelEnabled: e.target.checked })}
+                      />
+                      Exponer con Cloudflare Tunnel
+                    </label>
+                  </div>
+                  <div className="form-group checkbox">
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={agentConfig.autoStartAgent}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, autoStartAgent: e.target.checked })}
+                      />
+                      Iniciar el agente al abrir la aplicación
+                    </label>
+                  </div>
+                  <div className="form-group checkbox">
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={agentConfig.autoStartCameras}
+                        onChange={(e) => setAgentConfig({ ...agentConfig, autoStartCameras: e.target.checked })}
+                      />
+                      Iniciar las cámaras habilitadas con el agente
+                    </label>
+                  </div>
+                  {configErrors
+                    .filter((e) => !["locationName", "locationId", "serverUrl", "tunnelHostname"].includes(e.field))
+                    .map((e) => (
+                      <div key={`${e.field}-${e.message}`} className="field-error">{e.field}: {e.message}</div>
+                    ))}
+                  <p className="form-hint">Los cambios de servidor y túnel se aplican al reiniciar el agente.</p>
+                  <button type="submit" className="btn btn-start" disabled={loading}>
+                    {loading ? "Guardando..." : "💾 Guardar configuración"}
+                  </button>
+                </form>
+              </section>
+            )}
+
+            <section className="card">
+              <h2>Importar del agente anterior</h2>
+              <div className="add-camera-form">
+                <div className="form-group">
+                  <label>cameras.json (stream-agent-electron o servidor Node)</label>
+                  <input
+                    type="text"
+                    placeholder="C:\Users\...\stream-agent-electron\cameras.json"
+                    value={legacyPaths.cameras}
+                    onChange={(e) => { setLegacyPaths({ ...legacyPaths, cameras: e.target.value }); setLegacyImport(null); }}
+                  />
+                </div>
+                <div className="form-group">
+                  <label>config.json (stream-agent-electron)</label>
+                  <input
+                    type="text"
+                    value={legacyPaths.config}
+                    onChange={(e) => { setLegacyPaths({ ...legacyPaths, config: e.target.value }); setLegacyImport(null); }}
+                  />
+                </div>
+                <div className="dev-actions">
+                  <button
+                    className="btn btn-secondary"
+                    disabled={loading || (!legacyPaths.cameras && !legacyPaths.config)}
+                    onClick={() => handleLegacyImport(true)}
+                  >
+                    🔍 Vista previa
+                  </button>
+                  <button
+                    className="btn btn-start"
+                    disabled={loading || !legacyImport?.dryRun}
+                    onClick={() => handleLegacyImport(false)}
+                  >
+                    📥 Importar
+                  </button>
+                </div>
+                {legacyImport && (
+                  <div className="settings-info">
+                    {legacyImport.camerasFormat && (
+                      <p>Formato: {LEGACY_FORMAT_LABELS[legacyImport.camerasFormat] ?? legacyImport.camerasFormat}</p>
+                    )}
+                    <h3>📷 Cámaras {legacyImport.dryRun ? "a importar" : "importadas"} ({legacyImport.cameras.length})</h3>
+                    <ul>
+                      {legacyImport.cameras.map((camera) => (
+                        <li key={camera.id}><strong>{camera.name}</strong> ({camera.id}) <code>{camera.rtspUrl}</code></li>
+                      ))}
+                    </ul>
+                    {legacyImport.config && (
+                      <p>Ubicación: {legacyImport.config.locationName} ({legacyImport.config.locationId})</p>
+                    )}
+                    {legacyImport.issues.length > 0 && (
+                      <>
+                        <h3>⚠️ No importado</h3>
+                        <ul>
+                          {legacyImport.issues.map((issue, i) => (
+                            <li key={i} className="field-error">
+                              {issue.camera && <strong>{issue.camera}: </strong>}
+                              {issue.field} - {issue.message}
+                            </li>
+                          ))}
+                        </ul>
+                      </>
+                    )}
+                  </div>
+                )}
+              </div>
+            </section>
+
             <section className="card">
               <h2>Configuración</h2>
               <div className="settings-info">